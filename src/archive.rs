@@ -0,0 +1,13 @@
+use std::fs;
+use zed_extension_api::Result;
+
+/// Extracts the zip archive at `archive_path` into the directory `dir`.
+pub fn extract_zip(archive_path: &str, dir: &str) -> Result<()> {
+    let file =
+        fs::File::open(archive_path).map_err(|e| format!("failed to open {archive_path}: {e}"))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("failed to read archive {archive_path}: {e}"))?;
+    archive
+        .extract(dir)
+        .map_err(|e| format!("failed to extract {archive_path}: {e}"))
+}
@@ -0,0 +1,109 @@
+use crate::is_file;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zed::http_client::{HttpMethod, HttpRequest, RedirectPolicy};
+use zed_extension_api::{self as zed, Result, serde_json};
+
+const METADATA_FILE_NAME: &str = "cache.json";
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Entry {
+    /// When the file was last fetched or confirmed to be up to date, in seconds since the Unix
+    /// epoch.
+    fetched_at: u64,
+    /// The URL the file was fetched from. A file is fetched again if its URL changes, e.g. because
+    /// a mirror was configured.
+    url: String,
+    etag: Option<String>,
+}
+
+pub enum Fetched {
+    /// The cached file is up to date.
+    Fresh,
+    /// Refreshing the cached file failed, so the previous copy is used instead.
+    Stale(String),
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+fn read_metadata() -> HashMap<String, Entry> {
+    fs::read_to_string(METADATA_FILE_NAME)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn write_metadata(metadata: &HashMap<String, Entry>) -> Result<()> {
+    let as_str = serde_json::to_string(metadata)
+        .map_err(|e| format!("failed to serialize cache metadata: {e}"))?;
+    fs::write(METADATA_FILE_NAME, as_str)
+        .map_err(|e| format!("failed to write {METADATA_FILE_NAME}: {e}"))
+}
+
+fn fetch_etag(url: &str) -> Option<String> {
+    let response = HttpRequest::builder()
+        .method(HttpMethod::Head)
+        .url(url)
+        .redirect_policy(RedirectPolicy::FollowAll)
+        .build()
+        .ok()?
+        .fetch()
+        .ok()?;
+    response
+        .headers
+        .into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("etag"))
+        .map(|(_, value)| value)
+}
+
+/// Makes sure `file_name` holds a copy of `url` that was fetched at most `max_age` seconds ago.
+/// If refreshing fails but a previous copy exists, the previous copy is kept.
+pub fn fetch(url: &str, file_name: &str, max_age: u64) -> Result<Fetched> {
+    let mut metadata = read_metadata();
+    let now = now();
+
+    let exists = is_file(file_name);
+    let entry = metadata.entry(file_name.to_string()).or_default();
+    let is_same_url = entry.url == url;
+    if exists && is_same_url && now.saturating_sub(entry.fetched_at) < max_age {
+        return Ok(Fetched::Fresh);
+    }
+
+    let etag = fetch_etag(url);
+    if exists && is_same_url && etag.is_some() && etag == entry.etag {
+        entry.fetched_at = now;
+        write_metadata(&metadata)?;
+        return Ok(Fetched::Fresh);
+    }
+
+    // Download next to the cached file first so that a failed download doesn't clobber it.
+    let download_path = format!("{file_name}.download");
+    let downloaded = zed::download_file(url, &download_path, zed::DownloadedFileType::Uncompressed)
+        .and_then(|_| {
+            fs::rename(&download_path, file_name)
+                .map_err(|e| format!("failed to move {download_path} to {file_name}: {e}"))
+        });
+    if let Err(e) = downloaded {
+        fs::remove_file(&download_path).ok();
+        let e = format!("failed to download {url}: {e}");
+        return match exists {
+            true => Ok(Fetched::Stale(e)),
+            false => Err(e),
+        };
+    }
+
+    *entry = Entry {
+        fetched_at: now,
+        url: url.to_string(),
+        etag,
+    };
+    write_metadata(&metadata)?;
+    Ok(Fetched::Fresh)
+}
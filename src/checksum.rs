@@ -0,0 +1,157 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use zed_extension_api::{self as zed, Result};
+
+/// Names of release assets that list checksums for every other asset.
+const CHECKSUM_LIST_ASSET_NAMES: &[&str] = &[
+    "checksums.txt",
+    "sha256sums.txt",
+    "SHA256SUMS",
+    "SHA256SUMS.txt",
+];
+
+pub fn sha256_file(path: &str) -> Result<String> {
+    let bytes = fs::read(path).map_err(|e| format!("failed to read {path}: {e}"))?;
+    Ok(Sha256::digest(&bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Finds the checksum of `asset_name` in the checksum assets published with `release`, if there
+/// are any.
+pub fn find_published(release: &zed::GithubRelease, asset_name: &str) -> Result<Option<String>> {
+    let single_asset_name = format!("{asset_name}.sha256");
+    let Some(checksum_asset) = release.assets.iter().find(|asset| {
+        asset.name == single_asset_name || CHECKSUM_LIST_ASSET_NAMES.contains(&asset.name.as_str())
+    }) else {
+        return Ok(None);
+    };
+
+    let download_path = format!("{}.download", checksum_asset.name);
+    crate::download::download_file(&checksum_asset.download_url, &download_path)
+        .map_err(|e| format!("failed to download {}: {e}", checksum_asset.name))?;
+    let contents = fs::read_to_string(&download_path);
+    fs::remove_file(&download_path).ok();
+    let contents = contents.map_err(|e| format!("failed to read {}: {e}", checksum_asset.name))?;
+
+    match parse_checksum(&contents, &checksum_asset.name, asset_name) {
+        Some(hash) => Ok(Some(hash)),
+        None => Err(format!(
+            "{} doesn't contain a checksum for {asset_name}",
+            checksum_asset.name
+        )),
+    }
+}
+
+/// Finds the checksum of `asset_name` in `contents`, the contents of the checksum asset named
+/// `checksum_asset_name`.
+fn parse_checksum(contents: &str, checksum_asset_name: &str, asset_name: &str) -> Option<String> {
+    let is_single_asset = checksum_asset_name == format!("{asset_name}.sha256");
+    // Lines are either `<hash>` for single-asset files or `<hash>  <file name>` for lists, where the
+    // file name may be prefixed with `*` to mark binary mode.
+    contents.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        let matches = match parts.next() {
+            Some(name) => name.trim_start_matches('*') == asset_name,
+            None => is_single_asset,
+        };
+        matches.then(|| hash.to_ascii_lowercase())
+    })
+}
+
+/// Returns the SHA-256 hash of the file at `path`, failing unless it's `expected`.
+pub fn verify(path: &str, expected: &str) -> Result<String> {
+    let actual = sha256_file(path)?;
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(format!(
+            "checksum mismatch for {path}: expected SHA-256 {expected}, but got {actual}. Refusing to install it."
+        ));
+    }
+    Ok(actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+    const OTHER_HASH: &str = "60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752";
+
+    #[test]
+    fn finds_hash_in_single_asset_file() {
+        let contents = format!("{HASH}\n");
+        assert_eq!(
+            parse_checksum(&contents, "luau-lsp-linux.zip.sha256", "luau-lsp-linux.zip"),
+            Some(HASH.to_string())
+        );
+    }
+
+    #[test]
+    fn finds_named_hash_in_single_asset_file() {
+        let contents = format!("{HASH}  luau-lsp-linux.zip\n");
+        assert_eq!(
+            parse_checksum(&contents, "luau-lsp-linux.zip.sha256", "luau-lsp-linux.zip"),
+            Some(HASH.to_string())
+        );
+    }
+
+    #[test]
+    fn finds_hash_in_list() {
+        let contents = format!(
+            "{OTHER_HASH}  luau-lsp-macos.zip\n\n{HASH}  luau-lsp-linux.zip\n{OTHER_HASH}  luau-lsp-win64.zip\n"
+        );
+        assert_eq!(
+            parse_checksum(&contents, "checksums.txt", "luau-lsp-linux.zip"),
+            Some(HASH.to_string())
+        );
+    }
+
+    #[test]
+    fn finds_binary_mode_hash_in_list() {
+        let contents = format!("{HASH} *luau-lsp-linux.zip\n");
+        assert_eq!(
+            parse_checksum(&contents, "SHA256SUMS", "luau-lsp-linux.zip"),
+            Some(HASH.to_string())
+        );
+    }
+
+    #[test]
+    fn lowercases_hashes() {
+        let contents = format!("{}  luau-lsp-linux.zip\n", HASH.to_ascii_uppercase());
+        assert_eq!(
+            parse_checksum(&contents, "checksums.txt", "luau-lsp-linux.zip"),
+            Some(HASH.to_string())
+        );
+    }
+
+    #[test]
+    fn ignores_other_assets() {
+        let contents = format!(
+            "{OTHER_HASH}  luau-lsp-linux.zip.sig\n{OTHER_HASH}  luau-lsp-linux-arm64.zip\n"
+        );
+        assert_eq!(
+            parse_checksum(&contents, "checksums.txt", "luau-lsp-linux.zip"),
+            None
+        );
+    }
+
+    #[test]
+    fn ignores_unnamed_hashes_in_lists() {
+        let contents = format!("{OTHER_HASH}\n");
+        assert_eq!(
+            parse_checksum(&contents, "checksums.txt", "luau-lsp-linux.zip"),
+            None
+        );
+    }
+
+    #[test]
+    fn ignores_unnamed_hashes_for_other_assets() {
+        let contents = format!("{OTHER_HASH}\n");
+        assert_eq!(
+            parse_checksum(&contents, "luau-lsp-macos.zip.sha256", "luau-lsp-linux.zip"),
+            None
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use std::fs;
+use std::thread;
+use std::time::Duration;
+use zed_extension_api::{self as zed, Result};
+
+const ATTEMPTS: u32 = 4;
+/// The delay before the first retry, which doubles with every retry after it.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Downloads the uncompressed file at `url` to `path` like `zed::download_file`, but retries with
+/// exponential backoff if the download fails.
+pub fn download_file(url: &str, path: &str) -> Result<()> {
+    let mut delay = INITIAL_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match zed::download_file(url, path, zed::DownloadedFileType::Uncompressed) {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt == ATTEMPTS => {
+                fs::remove_file(path).ok();
+                return Err(format!("{e} (after {ATTEMPTS} attempts)"));
+            }
+            Err(_) => {
+                fs::remove_file(path).ok();
+                thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
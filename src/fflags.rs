@@ -0,0 +1,466 @@
+use crate::settings::{FFlagSource, NewSolver, Settings};
+use crate::suggest;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use zed_extension_api::{self as zed, Result, serde_json};
+
+pub const PREFIXES: &[&str] = &["FFlag", "FInt", "DFFlag", "DFInt"];
+/// The fflag presets that are always available. Custom presets with the same name replace them,
+/// which allows updating them without a new release of the extension.
+const BUILT_IN_PRESETS: &[(&str, &[(&str, &str)])] = &[(
+    "new_solver",
+    &[
+        ("LuauSolverV2", "true"),
+        ("LuauNewSolverPopulateTableLocations", "true"),
+        ("LuauNewSolverPrePopulateClasses", "true"),
+    ],
+)];
+/// The directory snapshots are written to when `fflags.snapshot` names a file that doesn't exist
+/// yet.
+const SNAPSHOTS_DIR_NAME: &str = "fflag-snapshots";
+
+/// The type of value an fflag has, which is determined by its prefix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Bool,
+    Int,
+}
+
+impl Kind {
+    fn of_prefix(prefix: &str) -> Self {
+        match prefix {
+            "FInt" | "DFInt" => Kind::Int,
+            _ => Kind::Bool,
+        }
+    }
+
+    fn accepts(self, value: &str) -> bool {
+        match self {
+            Kind::Bool => value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false"),
+            Kind::Int => value.parse::<i64>().is_ok(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Bool => "a boolean flag",
+            Kind::Int => "an integer flag",
+        }
+    }
+
+    fn describe(self) -> String {
+        match self {
+            Kind::Bool => format!(
+                "{}, so its value must be \"true\" or \"false\"",
+                self.name()
+            ),
+            Kind::Int => format!("{}, so its value must be an integer", self.name()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FFlag {
+    pub kind: Kind,
+    pub value: String,
+}
+
+/// Splits a Luau fflag name like `FIntLuauFoo` into its kind and its name without the prefix.
+fn split_prefix(name: &str) -> Option<(Kind, &str)> {
+    PREFIXES.iter().find_map(|prefix| {
+        let name = name.strip_prefix(prefix)?;
+        name.starts_with("Luau")
+            .then_some((Kind::of_prefix(prefix), name))
+    })
+}
+
+pub fn get_url(base_url: &str, source: &FFlagSource) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    match &source.channel {
+        Some(channel) => format!(
+            "{base_url}/v2/settings/application/{}/bucket/{channel}",
+            source.application
+        ),
+        None => format!(
+            "{base_url}/v1/settings/application?applicationName={}",
+            source.application
+        ),
+    }
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '-' {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+/// Returns the name of the file the fflags of `source` are cached in, which is different for every
+/// source so that switching between them doesn't require downloading them again.
+pub fn get_file_name(source: &FFlagSource) -> String {
+    let name = match &source.channel {
+        Some(channel) => format!("{}-{channel}", source.application),
+        None => source.application.clone(),
+    };
+    format!("fflags-{}.json", sanitize_file_name(&name))
+}
+
+/// Reads the Luau fflags in the fflags file `file_name`, keyed by their names without prefixes.
+pub fn read_synced(file_name: &str) -> Result<HashMap<String, FFlag>> {
+    let as_str =
+        fs::read_to_string(file_name).map_err(|e| format!("failed to read {file_name}: {e}"))?;
+    let json: serde_json::Value =
+        serde_json::from_str(&as_str).map_err(|e| format!("failed to parse {file_name}: {e}"))?;
+    let Some(json_map) = json.as_object() else {
+        return Err("failed to sync fflags: error when parsing fetched fflags: fflags must be an object, but isn't.".into());
+    };
+    let Some(app_settings_val) = json_map.get("applicationSettings") else {
+        return Err("failed to sync fflags: error when reading parsed fflags: json.applicationSettings must exist, but doesn't.".into());
+    };
+    let Some(app_settings) = app_settings_val.as_object() else {
+        return Err("failed to sync fflags: error when reading parsed fflags: json.applicationSettings must be an object, but isn't.".into());
+    };
+
+    let mut fflags = HashMap::new();
+    for (name, value) in app_settings.iter() {
+        let Some(value) = value.as_str() else {
+            return Err("failed to sync fflags: error when reading parsed fflags: all values in json.applicationSettings must be strings, but one or more aren't.".into());
+        };
+        if let Some((kind, name)) = split_prefix(name) {
+            fflags.insert(
+                name.to_string(),
+                FFlag {
+                    kind,
+                    value: value.to_string(),
+                },
+            );
+        }
+    }
+    Ok(fflags)
+}
+
+/// Checks that the values of the fflags set in `setting` fit the kinds of the flags, which are
+/// taken from `synced` or from the prefix of the flag's name. Flags that aren't in `synced`, and
+/// prefixes that don't match the kind of the synced flag, are reported as warnings, as they're
+/// likely mistakes.
+fn check_fflags(
+    fflags: &HashMap<String, String>,
+    setting: &str,
+    synced: Option<&HashMap<String, FFlag>>,
+    warnings: &mut Vec<String>,
+) -> Result<()> {
+    let mut names: Vec<&String> = fflags.keys().collect();
+    names.sort();
+
+    let mut errors = Vec::new();
+    for name in names {
+        let value = &fflags[name];
+        if name.is_empty() || value.is_empty() {
+            errors.push("all fflags must have a non-empty name and value.".to_string());
+            continue;
+        }
+
+        let (prefix_kind, unprefixed_name) = match split_prefix(name) {
+            Some((kind, unprefixed_name)) => (Some(kind), unprefixed_name),
+            None => (None, name.as_str()),
+        };
+        let synced_kind = match synced {
+            Some(synced) => match synced.get(unprefixed_name) {
+                Some(fflag) => {
+                    if let Some(prefix_kind) = prefix_kind
+                        && prefix_kind != fflag.kind
+                    {
+                        warnings.push(format!(
+                            "the prefix of `{name}` in `{setting}` makes it {}, but the synced fflag `{unprefixed_name}` is {}.",
+                            prefix_kind.name(),
+                            fflag.kind.name()
+                        ));
+                    }
+                    Some(fflag.kind)
+                }
+                None => {
+                    let mut warning = format!(
+                        "the fflag `{name}` in `{setting}` isn't one of the synced Luau fflags."
+                    );
+                    if let Some(closest) = suggest::closest_match(
+                        unprefixed_name,
+                        synced.keys().map(|name| name.as_str()),
+                    ) {
+                        warning.push_str(&format!(" Did you mean `{closest}`?"));
+                    }
+                    warnings.push(warning);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        if let Some(kind) = synced_kind.or(prefix_kind)
+            && !kind.accepts(value)
+        {
+            errors.push(format!(
+                "`{name}` is {}, but got \"{value}\".",
+                kind.describe()
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!("invalid fflags in `{setting}`:");
+    for error in errors {
+        message.push_str(&format!("\n- {error}"));
+    }
+    Err(message)
+}
+
+/// Sets the fflags in `setting_fflags` in `fflags`, under their names without prefixes, as that's
+/// how synced fflags are keyed and how luau-lsp expects them. Names are applied in sorted order, so
+/// if a flag is set both with and without its prefix, the unprefixed one is used.
+fn apply_fflags(fflags: &mut BTreeMap<String, String>, setting_fflags: &HashMap<String, String>) {
+    let mut names: Vec<&String> = setting_fflags.keys().collect();
+    names.sort();
+    for name in names {
+        let unprefixed_name = split_prefix(name).map_or(name.as_str(), |(_, name)| name);
+        fflags.insert(unprefixed_name.to_string(), setting_fflags[name].clone());
+    }
+}
+
+/// Reads the fflag snapshot at `path` in the worktree, or returns `None` if it doesn't exist.
+fn read_snapshot(worktree: &zed::Worktree, path: &str) -> Result<Option<BTreeMap<String, String>>> {
+    let Ok(as_str) = worktree.read_text_file(path) else {
+        return Ok(None);
+    };
+    serde_json::from_str(&as_str)
+        .map(Some)
+        .map_err(|e| format!("failed to parse fflag snapshot {path}: {e}"))
+}
+
+/// Writes `fflags` as a snapshot for the worktree at `root_path` and returns the path it was
+/// written to, or `None` if the snapshot there already has the same contents.
+fn write_snapshot(root_path: &str, fflags: &BTreeMap<String, String>) -> Result<Option<String>> {
+    let path = format!(
+        "{SNAPSHOTS_DIR_NAME}/{}.json",
+        sanitize_file_name(root_path.trim_start_matches('/'))
+    );
+    let as_str = serde_json::to_string_pretty(fflags)
+        .map_err(|e| format!("failed to serialize fflag snapshot: {e}"))?
+        + "\n";
+    if fs::read_to_string(&path).is_ok_and(|previous| previous == as_str) {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(SNAPSHOTS_DIR_NAME)
+        .map_err(|e| format!("failed to create directory {SNAPSHOTS_DIR_NAME}: {e}"))?;
+    fs::write(&path, as_str).map_err(|e| format!("failed to write {path}: {e}"))?;
+    Ok(Some(path))
+}
+
+/// Returns the fflags to pass to luau-lsp for the worktree.
+///
+/// The fflags start out as the synced fflags, or the fflags in the `fflags.snapshot` file if it
+/// exists. The enabled presets are applied over them in order, and `fflags.override` over that. If
+/// `fflags.snapshot` is set but the file doesn't exist, the result is written as a snapshot that
+/// can be copied there, which is warned about whenever its contents change.
+pub fn resolve(
+    settings: &Settings,
+    worktree: &zed::Worktree,
+    warnings: &mut Vec<String>,
+) -> Result<BTreeMap<String, String>> {
+    let mut fflags = BTreeMap::new();
+
+    let snapshot = match &settings.fflags.snapshot {
+        Some(path) => read_snapshot(worktree, path)?,
+        None => None,
+    };
+    let synced = match &snapshot {
+        Some(snapshot) => {
+            fflags.extend(snapshot.clone());
+            None
+        }
+        None => {
+            let file_name = get_file_name(&settings.fflags.source);
+            match settings.fflags.sync
+                && crate::fetch_file(
+                    &get_url(&settings.mirrors.fflags, &settings.fflags.source),
+                    &file_name,
+                    settings,
+                    warnings,
+                )? {
+                true => Some(read_synced(&file_name)?),
+                false => None,
+            }
+        }
+    };
+    if let Some(synced) = &synced {
+        for (name, fflag) in synced {
+            fflags.insert(name.clone(), fflag.value.clone());
+        }
+    }
+
+    let enable_new_solver = match &settings.fflags.enable_new_solver {
+        NewSolver::Enabled(enabled) => *enabled,
+        NewSolver::Auto(_) => match fflags.get("LuauSolverV2") {
+            Some(value) => value.eq_ignore_ascii_case("true"),
+            None => {
+                if snapshot.is_none() && synced.is_none() {
+                    warnings.push("`fflags.enable_new_solver` is \"auto\", but fflags aren't synced, so the new solver is disabled.".into());
+                }
+                false
+            }
+        },
+    };
+    let mut presets: Vec<&str> = settings.fflags.presets.iter().map(String::as_str).collect();
+    if enable_new_solver && !presets.contains(&"new_solver") {
+        presets.insert(0, "new_solver");
+    }
+    for preset in presets {
+        if let Some(preset_fflags) = settings.fflags.custom_presets.get(preset) {
+            check_fflags(
+                preset_fflags,
+                &format!("fflags.custom_presets.{preset}"),
+                synced.as_ref(),
+                warnings,
+            )?;
+            apply_fflags(&mut fflags, preset_fflags);
+        } else if let Some((_, preset_fflags)) =
+            BUILT_IN_PRESETS.iter().find(|(name, _)| *name == preset)
+        {
+            for (name, value) in preset_fflags.iter() {
+                fflags.insert(name.to_string(), value.to_string());
+            }
+        } else {
+            let mut warning = format!("unknown fflag preset `{preset}` in `fflags.presets`.");
+            let names = BUILT_IN_PRESETS
+                .iter()
+                .map(|(name, _)| *name)
+                .chain(settings.fflags.custom_presets.keys().map(String::as_str));
+            if let Some(closest) = suggest::closest_match(preset, names) {
+                warning.push_str(&format!(" Did you mean `{closest}`?"));
+            }
+            warnings.push(warning);
+        }
+    }
+
+    check_fflags(
+        &settings.fflags.overrides,
+        "fflags.override",
+        synced.as_ref(),
+        warnings,
+    )?;
+    apply_fflags(&mut fflags, &settings.fflags.overrides);
+
+    if let Some(snapshot_path) = &settings.fflags.snapshot
+        && snapshot.is_none()
+    {
+        let root_path = worktree.root_path();
+        if let Some(written_path) = write_snapshot(&root_path, &fflags)? {
+            let current_dir = std::env::current_dir().unwrap();
+            warnings.push(format!(
+                "the fflag snapshot {snapshot_path} doesn't exist, so the current fflags were used and written to {}/{written_path}; copy that file to {root_path}/{snapshot_path} to use them from now on.",
+                current_dir.display()
+            ));
+        }
+    }
+
+    Ok(fflags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fflags(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn synced(entries: &[(&str, Kind)]) -> HashMap<String, FFlag> {
+        entries
+            .iter()
+            .map(|&(name, kind)| {
+                let fflag = FFlag {
+                    kind,
+                    value: String::new(),
+                };
+                (name.to_string(), fflag)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prefixed_fflags_replace_synced_ones() {
+        let mut resolved = BTreeMap::from([
+            ("LuauSolverV2".to_string(), "false".to_string()),
+            ("LuauTypeSolverLimit".to_string(), "10".to_string()),
+        ]);
+        apply_fflags(
+            &mut resolved,
+            &fflags(&[
+                ("FFlagLuauSolverV2", "true"),
+                ("FIntLuauTypeSolverLimit", "20"),
+            ]),
+        );
+        assert_eq!(
+            resolved,
+            BTreeMap::from([
+                ("LuauSolverV2".to_string(), "true".to_string()),
+                ("LuauTypeSolverLimit".to_string(), "20".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn unprefixed_fflags_take_precedence_over_prefixed_ones() {
+        let mut resolved = BTreeMap::new();
+        apply_fflags(
+            &mut resolved,
+            &fflags(&[("FFlagLuauSolverV2", "false"), ("LuauSolverV2", "true")]),
+        );
+        assert_eq!(
+            resolved,
+            BTreeMap::from([("LuauSolverV2".to_string(), "true".to_string())])
+        );
+    }
+
+    #[test]
+    fn mismatched_prefixes_are_warned_about() {
+        let synced = synced(&[("LuauSolverV2", Kind::Bool)]);
+        let mut warnings = Vec::new();
+        check_fflags(
+            &fflags(&[("FIntLuauSolverV2", "true")]),
+            "fflags.override",
+            Some(&synced),
+            &mut warnings,
+        )
+        .unwrap();
+        assert_eq!(
+            warnings,
+            [
+                "the prefix of `FIntLuauSolverV2` in `fflags.override` makes it an integer flag, but the synced fflag `LuauSolverV2` is a boolean flag."
+            ]
+        );
+    }
+
+    #[test]
+    fn values_are_checked_against_the_synced_kind() {
+        let synced = synced(&[("LuauSolverV2", Kind::Bool)]);
+        let mut warnings = Vec::new();
+        let error = check_fflags(
+            &fflags(&[("FIntLuauSolverV2", "1")]),
+            "fflags.override",
+            Some(&synced),
+            &mut warnings,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "invalid fflags in `fflags.override`:\n- `FIntLuauSolverV2` is a boolean flag, so its value must be \"true\" or \"false\", but got \"1\"."
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use zed::http_client::{HttpMethod, HttpRequest, RedirectPolicy};
+use zed_extension_api::{self as zed, Result, serde_json};
+
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// A version of a tool to install, as written in settings.
+pub enum VersionSpec {
+    /// An exact release tag, e.g. `1.40.0`.
+    Tag(String),
+    /// A semver requirement, e.g. `^1.40`, resolved against the repository's releases.
+    Requirement(semver::VersionReq),
+}
+
+impl VersionSpec {
+    pub fn parse(version: &str) -> Self {
+        let version = version.trim();
+        if parse_tag_version(version).is_some() {
+            return VersionSpec::Tag(version.to_string());
+        }
+        match semver::VersionReq::parse(version) {
+            Ok(req) => VersionSpec::Requirement(req),
+            // Not every repository uses semver tags, so fall back to treating it as a tag.
+            Err(_) => VersionSpec::Tag(version.to_string()),
+        }
+    }
+
+    pub fn matches(&self, tag: &str) -> bool {
+        match self {
+            VersionSpec::Tag(t) => t == tag,
+            VersionSpec::Requirement(req) => {
+                parse_tag_version(tag).is_some_and(|version| req.matches(&version))
+            }
+        }
+    }
+}
+
+impl fmt::Display for VersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionSpec::Tag(tag) => f.write_str(tag),
+            VersionSpec::Requirement(req) => write!(f, "{req}"),
+        }
+    }
+}
+
+pub fn parse_tag_version(tag: &str) -> Option<semver::Version> {
+    semver::Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
+
+#[derive(Deserialize)]
+struct ApiRelease {
+    tag_name: String,
+    draft: bool,
+    prerelease: bool,
+    assets: Vec<ApiAsset>,
+}
+
+#[derive(Deserialize)]
+struct ApiAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl From<ApiRelease> for zed::GithubRelease {
+    fn from(release: ApiRelease) -> Self {
+        zed::GithubRelease {
+            version: release.tag_name,
+            assets: release
+                .assets
+                .into_iter()
+                .map(|asset| zed::GithubReleaseAsset {
+                    name: asset.name,
+                    download_url: asset.browser_download_url,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Fetches `path` from the GitHub REST API at `api_url`. `what` describes the response in errors.
+pub fn get<T: DeserializeOwned>(api_url: &str, path: &str, what: &str) -> Result<T> {
+    let response = HttpRequest::builder()
+        .method(HttpMethod::Get)
+        .url(format!("{api_url}{path}"))
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "zed-luau")
+        .redirect_policy(RedirectPolicy::FollowAll)
+        .build()?
+        .fetch()
+        .map_err(|e| format!("failed to fetch {what}: {e}"))?;
+    serde_json::from_slice(&response.body).map_err(|e| format!("failed to parse {what}: {e}"))
+}
+
+/// Returns the most recent published releases of `repo`, newest first, including pre-releases.
+fn list_api_releases(api_url: &str, repo: &str) -> Result<Vec<ApiRelease>> {
+    let releases: Vec<ApiRelease> = get(
+        api_url,
+        &format!("/repos/{repo}/releases?per_page=100"),
+        &format!("releases of {repo}"),
+    )?;
+    Ok(releases
+        .into_iter()
+        .filter(|release| !release.draft)
+        .collect())
+}
+
+/// Lists the most recent published releases of `repo` using the GitHub REST API at `api_url`,
+/// newest first.
+pub fn list_releases(
+    api_url: &str,
+    repo: &str,
+    include_pre_releases: bool,
+) -> Result<Vec<zed::GithubRelease>> {
+    Ok(list_api_releases(api_url, repo)?
+        .into_iter()
+        .filter(|release| include_pre_releases || !release.prerelease)
+        .map(zed::GithubRelease::from)
+        .collect())
+}
+
+/// Returns the latest release of `repo`, or the latest pre-release if `pre_release` is true. If
+/// `require_assets` is true, releases without assets are skipped.
+///
+/// Zed's own lookup is used with the default API URL. Other API URLs are queried directly, so that
+/// no request goes to GitHub when a mirror is configured.
+pub fn latest_release(
+    api_url: &str,
+    repo: &str,
+    pre_release: bool,
+    require_assets: bool,
+) -> Result<zed::GithubRelease> {
+    if api_url == DEFAULT_API_URL {
+        return zed::latest_github_release(
+            repo,
+            zed::GithubReleaseOptions {
+                require_assets,
+                pre_release,
+            },
+        );
+    }
+    list_api_releases(api_url, repo)?
+        .into_iter()
+        .find(|release| {
+            release.prerelease == pre_release && !(require_assets && release.assets.is_empty())
+        })
+        .map(zed::GithubRelease::from)
+        .ok_or_else(|| format!("no release of {repo} found at {api_url}"))
+}
+
+/// Returns the release of `repo` tagged `tag`. Like `latest_release`, only uses `api_url` if it
+/// isn't the default.
+fn release_by_tag(api_url: &str, repo: &str, tag: &str) -> Result<zed::GithubRelease> {
+    if api_url == DEFAULT_API_URL {
+        return zed::github_release_by_tag_name(repo, tag);
+    }
+    let release: ApiRelease = get(
+        api_url,
+        &format!("/repos/{repo}/releases/tags/{tag}"),
+        &format!("release {tag:?} of {repo}"),
+    )?;
+    Ok(release.into())
+}
+
+/// Returns the release of `repo` that `version` resolves to. Requirements resolve to the highest
+/// matching release, which is only a pre-release if `include_pre_releases` is true.
+pub fn release_for_version(
+    api_url: &str,
+    repo: &str,
+    version: &VersionSpec,
+    include_pre_releases: bool,
+) -> Result<zed::GithubRelease> {
+    match version {
+        VersionSpec::Tag(tag) => release_by_tag(api_url, repo, tag)
+            .map_err(|e| format!("failed to find release {tag:?} of {repo}: {e}")),
+        VersionSpec::Requirement(req) => list_releases(api_url, repo, include_pre_releases)?
+            .into_iter()
+            .filter_map(|release| {
+                let version = parse_tag_version(&release.version)?;
+                req.matches(&version).then_some((version, release))
+            })
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, release)| release)
+            .ok_or_else(|| format!("no release of {repo} matches version requirement \"{req}\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_exact_versions_as_tags() {
+        for version in ["1.40.0", "v1.40.0", " 1.40.0 ", "1.41.0-rc.1"] {
+            assert!(
+                matches!(VersionSpec::parse(version), VersionSpec::Tag(ref tag) if tag == version.trim()),
+                "{version:?} should be a tag"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_ranges_as_requirements() {
+        for version in ["^1.40", "~1.40.1", ">=1.40, <2", "1.40", "1", "*"] {
+            assert!(
+                matches!(VersionSpec::parse(version), VersionSpec::Requirement(_)),
+                "{version:?} should be a requirement"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_other_versions_as_tags() {
+        assert!(
+            matches!(VersionSpec::parse("nightly"), VersionSpec::Tag(ref tag) if tag == "nightly")
+        );
+    }
+
+    #[test]
+    fn tags_match_exactly() {
+        let version = VersionSpec::parse("1.40.0");
+        assert!(version.matches("1.40.0"));
+        assert!(!version.matches("v1.40.0"));
+        assert!(!version.matches("1.40.1"));
+    }
+
+    #[test]
+    fn requirements_match_tags_with_or_without_v() {
+        let version = VersionSpec::parse("^1.40");
+        assert!(version.matches("1.40.0"));
+        assert!(version.matches("v1.45.2"));
+        assert!(!version.matches("1.39.9"));
+        assert!(!version.matches("2.0.0"));
+        assert!(!version.matches("nightly"));
+    }
+
+    #[test]
+    fn requirements_only_match_pre_releases_explicitly() {
+        assert!(!VersionSpec::parse("^1.40").matches("1.41.0-rc.1"));
+        assert!(VersionSpec::parse(">=1.41.0-rc.1").matches("1.41.0-rc.1"));
+    }
+}
@@ -0,0 +1,280 @@
+use crate::github::{self, VersionSpec};
+use crate::platform::{self, Target};
+use crate::{archive, checksum, download, is_file};
+use std::fs;
+use zed::LanguageServerId;
+use zed_extension_api::{self as zed, Result};
+
+/// The file in each version directory that records the SHA-256 hash of the archive the version was
+/// installed from.
+const ARCHIVE_SHA256_FILE_NAME: &str = "archive.sha256";
+
+/// A tool that is installed from the zip archives attached to its GitHub releases.
+pub struct Tool {
+    /// The name of the tool's executable, without `.exe`. It's also used in messages.
+    pub name: &'static str,
+    /// The directory the tool is installed in. Each version is installed in a subdirectory named
+    /// `{name}-{tag}`.
+    pub install_dir: &'static str,
+    /// Returns the names of the assets of release `tag` that can run on a target, most preferred
+    /// first.
+    pub asset_names: fn(Target, &str) -> Vec<String>,
+}
+
+/// Where and how to install a tool from.
+pub struct InstallOptions<'a> {
+    pub repo: &'a str,
+    pub api_url: &'a str,
+    /// The version to install, or `None` for the latest release.
+    pub version: Option<&'a VersionSpec>,
+    /// Whether pre-releases can be installed. If `version` is `None`, the latest pre-release is
+    /// installed instead of the latest release.
+    pub pre_release: bool,
+    /// The SHA-256 hash the downloaded archive must have. If `None`, the archive is verified
+    /// against a checksum published with the release, if any. An existing install is only used if
+    /// it was installed from an archive with this hash.
+    pub sha256: Option<&'a str>,
+    /// How many versions to keep installed, including the one being installed.
+    pub keep_versions: usize,
+    pub target: Target,
+}
+
+pub struct InstalledVersion {
+    pub tag: String,
+    pub dir_name: String,
+    pub binary_path: String,
+}
+
+/// Returns the file name of the executable `name` on the current platform.
+pub fn executable_name(name: &str) -> String {
+    let (platform, _) = zed::current_platform();
+    match platform {
+        zed::Os::Mac | zed::Os::Linux => name.to_string(),
+        zed::Os::Windows => format!("{name}.exe"),
+    }
+}
+
+impl Tool {
+    fn dir_name(&self, tag: &str) -> String {
+        format!("{}-{tag}", self.name)
+    }
+
+    /// Returns the path the tool's executable has when release `tag` is installed.
+    pub fn binary_path(&self, tag: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            self.install_dir,
+            self.dir_name(tag),
+            executable_name(self.name)
+        )
+    }
+
+    /// Returns the installed versions of the tool, newest first.
+    pub fn installed_versions(&self) -> Vec<InstalledVersion> {
+        let Ok(entries) = fs::read_dir(self.install_dir) else {
+            return Vec::new();
+        };
+        let prefix = format!("{}-", self.name);
+        let mut versions: Vec<InstalledVersion> = entries
+            .filter_map(|entry| {
+                let dir_name = entry.ok()?.file_name().into_string().ok()?;
+                let tag = dir_name.strip_prefix(&prefix)?.to_string();
+                if tag.ends_with(".download") {
+                    return None;
+                }
+                let binary_path = self.binary_path(&tag);
+                is_file(&binary_path).then_some(InstalledVersion {
+                    tag,
+                    dir_name,
+                    binary_path,
+                })
+            })
+            .collect();
+        versions.sort_by_cached_key(|version| {
+            std::cmp::Reverse(github::parse_tag_version(&version.tag))
+        });
+        versions
+    }
+
+    /// Returns the path to the executable of the newest installed version matching `version`, if
+    /// any. If `version` is `None`, any installed version matches.
+    pub fn find_installed(&self, version: Option<&VersionSpec>) -> Option<String> {
+        self.installed_versions()
+            .into_iter()
+            .find(|installed| version.is_none_or(|version| version.matches(&installed.tag)))
+            .map(|installed| installed.binary_path)
+    }
+
+    /// Fails unless the version whose executable is at `binary_path` was installed from an archive
+    /// with SHA-256 hash `expected`. Always succeeds if `expected` is `None`.
+    pub fn check_installed(&self, binary_path: &str, expected: Option<&str>) -> Result<()> {
+        let Some(expected) = expected else {
+            return Ok(());
+        };
+        let version_dir = binary_path.rsplit_once('/').map_or(".", |(dir, _)| dir);
+        match fs::read_to_string(format!("{version_dir}/{ARCHIVE_SHA256_FILE_NAME}")) {
+            Ok(actual) if actual.trim().eq_ignore_ascii_case(expected.trim()) => Ok(()),
+            Ok(actual) => Err(format!(
+                "the installed {} at {binary_path} was installed from an archive with SHA-256 {}, not {expected}. Refusing to run it.",
+                self.name,
+                actual.trim()
+            )),
+            Err(_) => Err(format!(
+                "the installed {} at {binary_path} has no recorded SHA-256 hash, so it can't be checked against {expected}. Refusing to run it.",
+                self.name
+            )),
+        }
+    }
+
+    /// Installs the release of the tool described by `options`, unless it's already installed, and
+    /// returns the path to its executable. Adds a warning to `warnings`, which is only logged, if
+    /// the downloaded archive couldn't be verified.
+    pub fn install(
+        &self,
+        language_server_id: &LanguageServerId,
+        options: &InstallOptions,
+        warnings: &mut Vec<String>,
+    ) -> Result<String> {
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::CheckingForUpdate,
+        );
+        let release = match options.version {
+            Some(version) => github::release_for_version(
+                options.api_url,
+                options.repo,
+                version,
+                options.pre_release,
+            )?,
+            None => {
+                github::latest_release(options.api_url, options.repo, options.pre_release, true)?
+            }
+        };
+
+        let binary_path = self.binary_path(&release.version);
+        // An install that doesn't have the expected hash is replaced, which verifies the new
+        // archive against the hash.
+        if is_file(&binary_path) && self.check_installed(&binary_path, options.sha256).is_ok() {
+            return Ok(binary_path);
+        }
+
+        let asset = platform::find_asset(
+            &release,
+            &(self.asset_names)(options.target, &release.version),
+        )
+        .ok_or_else(|| {
+            format!(
+                "no {} build for {} in release {}",
+                self.name, options.target, release.version
+            )
+        })?;
+
+        fs::create_dir_all(self.install_dir)
+            .map_err(|e| format!("failed to create directory for {}: {e}", self.name))?;
+
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::Downloading,
+        );
+        let dir_name = self.dir_name(&release.version);
+        let verified = install_verified_zip(
+            &release,
+            asset,
+            &format!("{}/{dir_name}", self.install_dir),
+            &executable_name(self.name),
+            options.sha256,
+        )?;
+        if !verified {
+            warnings.push(format!(
+                "{} {} was installed without verifying it, as no SHA-256 hash is set for it and the release doesn't publish a checksum for {}.",
+                self.name, release.version, asset.name
+            ));
+        }
+
+        self.remove_old_versions(&dir_name, options.keep_versions)?;
+
+        Ok(binary_path)
+    }
+
+    /// Removes everything in the install directory except the newest `keep_versions` versions,
+    /// never removing the version in `keep_dir_name`.
+    fn remove_old_versions(&self, keep_dir_name: &str, keep_versions: usize) -> Result<()> {
+        let mut keep: Vec<String> = vec![keep_dir_name.to_string()];
+        keep.extend(
+            self.installed_versions()
+                .into_iter()
+                .map(|installed| installed.dir_name)
+                .filter(|dir_name| dir_name != keep_dir_name)
+                .take(keep_versions.saturating_sub(1)),
+        );
+
+        let entries = fs::read_dir(self.install_dir)
+            .map_err(|e| format!("failed to list {} directory {e}", self.install_dir))?;
+        for entry in entries {
+            let entry = entry
+                .map_err(|e| format!("failed to load {} directory entry {e}", self.install_dir))?;
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            if !keep.contains(&dir_name) {
+                fs::remove_dir_all(entry.path()).ok();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Installs the zip archive `asset` of `release` into `version_dir`, which must contain the
+/// executable `binary_name` once extracted. The archive is first verified against
+/// `expected_sha256`, or against a checksum published with the release if `expected_sha256` is
+/// `None`. Returns whether the archive was verified. Its hash is recorded in `version_dir` either
+/// way.
+///
+/// The archive is extracted into a temporary directory that is only moved to `version_dir` once
+/// the executable is in place, so an interrupted install never leaves behind a broken
+/// `version_dir`.
+fn install_verified_zip(
+    release: &zed::GithubRelease,
+    asset: &zed::GithubReleaseAsset,
+    version_dir: &str,
+    binary_name: &str,
+    expected_sha256: Option<&str>,
+) -> Result<bool> {
+    let archive_path = format!("{version_dir}.zip");
+    let download_dir = format!("{version_dir}.download");
+    fs::remove_dir_all(&download_dir).ok();
+
+    download::download_file(&asset.download_url, &archive_path)
+        .map_err(|e| format!("failed to download {}: {e}", asset.name))?;
+
+    let result = (|| {
+        let expected_sha256 = match expected_sha256 {
+            Some(sha256) => Some(sha256.to_string()),
+            None => checksum::find_published(release, &asset.name)?,
+        };
+        let sha256 = match &expected_sha256 {
+            Some(expected_sha256) => checksum::verify(&archive_path, expected_sha256)?,
+            None => checksum::sha256_file(&archive_path)?,
+        };
+        archive::extract_zip(&archive_path, &download_dir)?;
+
+        let binary_path = format!("{download_dir}/{binary_name}");
+        if !is_file(&binary_path) {
+            return Err(format!("{} doesn't contain {binary_name}", asset.name));
+        }
+        zed::make_file_executable(&binary_path)?;
+        let sha256_path = format!("{download_dir}/{ARCHIVE_SHA256_FILE_NAME}");
+        fs::write(&sha256_path, sha256)
+            .map_err(|e| format!("failed to write {sha256_path}: {e}"))?;
+
+        // Whatever is left of a previous install of this version is broken, as it would've been
+        // used otherwise.
+        fs::remove_dir_all(version_dir).ok();
+        fs::rename(&download_dir, version_dir)
+            .map_err(|e| format!("failed to move {download_dir} to {version_dir}: {e}"))?;
+        Ok(expected_sha256.is_some())
+    })();
+    fs::remove_file(&archive_path).ok();
+    if result.is_err() {
+        fs::remove_dir_all(&download_dir).ok();
+    }
+    result
+}
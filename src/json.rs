@@ -47,3 +47,27 @@ pub fn get_or_insert_array<'a>(map: &'a mut Map<String, Value>, key: &str) -> &'
         }
     }
 }
+
+/// Merges `other` into `base`. Objects are merged recursively, while any other value in `other`
+/// replaces the corresponding value in `base`.
+pub fn merge(base: &mut Value, other: Value) {
+    match (base, other) {
+        (Value::Object(base), Value::Object(other)) => {
+            for (key, value) in other {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, other) => *base = other,
+    }
+}
+
+/// Returns the value at `path` in nested objects, if there is one.
+pub fn get_path<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(value, |value, key| value.as_object()?.get(*key))
+}
@@ -1,197 +1,233 @@
-use serde::Deserialize;
-use std::collections::HashMap;
+use github::VersionSpec;
+use installer::{InstallOptions, Tool};
+use platform::Target;
+use settings::{Channel, SecurityLevel, Settings, get_extension_settings};
 use std::ffi::OsStr;
 use std::fs;
 use std::path::Path;
 use zed::lsp::CompletionKind;
-use zed::serde_json::{Map, Value};
+use zed::serde_json::Value;
 use zed::settings::LspSettings;
 use zed::{CodeLabel, CodeLabelSpan, LanguageServerId, serde_json};
 use zed_extension_api::{self as zed, Result};
 
+mod archive;
+mod cache;
+mod checksum;
+mod download;
+mod fflags;
+mod github;
+mod installer;
 mod json;
+mod platform;
+mod plugin;
 mod roblox;
+mod runtime;
+mod schema;
+mod settings;
+mod suggest;
+mod toolchain;
+mod tools;
+
+const DEFAULT_FFLAG_BASE_URL: &str = "https://clientsettingscdn.roblox.com";
+const DEFAULT_LUAU_LSP_REPO: &str = "JohnnyMorganz/luau-lsp";
+const DEFAULT_PROXY_REPO: &str = "4teapo/luau-lsp-proxy";
+// The proxy version installed by default. Other versions can be chosen with
+// `plugin.proxy_version`, but only if they're in `COMPATIBLE_PROXY_VERSIONS`.
+const PROXY_VERSION: &str = "v0.1.0";
+// The proxy versions whose command-line interface matches what `language_server_command` passes:
+// the port, the path to the luau-lsp binary, and then the arguments for luau-lsp (`lsp` onwards).
+const COMPATIBLE_PROXY_VERSIONS: &str = ">=0.1.0, <0.2.0";
 
-const FFLAG_URL: &str =
-    "https://clientsettingscdn.roblox.com/v1/settings/application?applicationName=PCDesktopClient";
-const FFLAG_PREFIXES: &[&str] = &["FFlag", "FInt", "DFFlag", "DFInt"];
-const FFLAG_FILE_NAME: &str = "fflags.json";
-const LUAU_LSP_BINARY_DIR_NAME: &str = "luau-lsp-binaries";
-const PROXY_BINARY_DIR_NAME: &str = "proxy-binaries";
-
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-struct Settings {
-    #[serde(rename = "luau-lsp")]
-    luau_lsp: serde_json::Map<String, serde_json::Value>,
-    roblox: RobloxSettings,
-    fflags: FFlagsSettings,
-    binary: BinarySettings,
-    plugin: PluginSettings,
-    definitions: Vec<String>,
-    documentation: Vec<String>,
+struct LuauExtension {
+    cached_binary_path: Option<String>,
 }
 
-impl Default for Settings {
-    fn default() -> Self {
-        Settings {
-            luau_lsp: Default::default(),
-            roblox: Default::default(),
-            fflags: Default::default(),
-            binary: Default::default(),
-            plugin: Default::default(),
-            definitions: Default::default(),
-            documentation: Default::default(),
-        }
-    }
+pub(crate) fn is_file(path: &str) -> bool {
+    fs::metadata(path).is_ok_and(|stat| stat.is_file())
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-struct RobloxSettings {
-    enabled: bool,
-    security_level: SecurityLevel,
-    download_api_documentation: bool,
-    download_definitions: bool,
+pub(crate) fn is_dir(path: &str) -> bool {
+    fs::metadata(path).is_ok_and(|stat| stat.is_dir())
 }
 
-impl Default for RobloxSettings {
-    fn default() -> Self {
-        Self {
-            enabled: false,
-            security_level: SecurityLevel::Plugin,
-            download_api_documentation: true,
-            download_definitions: true,
+fn is_path_absolute(path: &str) -> bool {
+    let (platform, _) = zed::current_platform();
+    match platform {
+        // We need to handle Windows manually because of our UNIX-based WASM environment
+        zed::Os::Windows => {
+            let mut chars = path.chars();
+            match (chars.next(), chars.next(), chars.next()) {
+                (Some(drive), Some(':'), Some(sep)) => {
+                    drive.is_ascii_alphabetic() && (sep == '\\' || sep == '/')
+                }
+                // UNC path
+                _ => path.starts_with("//") || path.starts_with("\\\\"),
+            }
         }
+        _ => Path::new(OsStr::new(path)).is_absolute(),
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "snake_case")]
-enum SecurityLevel {
-    RobloxScript,
-    LocalUser,
-    Plugin,
-    None,
+/// Resolves `path` relative to the root of `worktree` unless it's absolute.
+fn resolve_worktree_path(path: &str, worktree: &zed::Worktree) -> String {
+    match is_path_absolute(path) {
+        true => path.to_string(),
+        false => format!("{}/{path}", worktree.root_path()),
+    }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-struct FFlagsSettings {
-    enable_by_default: bool,
-    enable_new_solver: bool,
-    sync: bool,
-    #[serde(rename = "override")]
-    overrides: HashMap<String, String>,
+/// Returns the directory containing the type definitions for the configured runtime, if any are
+/// available without downloading anything.
+fn get_runtime_typedefs_dir(settings: &Settings, worktree: &zed::Worktree) -> Option<String> {
+    let runtime = settings.runtime.as_ref()?.typedefs_source();
+    if let Some(path) = &settings.runtime_typedefs {
+        return Some(resolve_worktree_path(path, worktree));
+    }
+    let dir = runtime::find_installed(runtime, settings.runtime_version.as_deref())?;
+    let current_dir = std::env::current_dir().ok()?;
+    Some(format!("{}/{dir}", current_dir.display()))
 }
 
-impl Default for FFlagsSettings {
-    fn default() -> Self {
-        Self {
-            enable_by_default: false,
-            enable_new_solver: false,
-            sync: true,
-            overrides: Default::default(),
+/// Installs the type definitions for `runtime`, or finds previously installed ones in offline mode
+/// or if installing them fails. Returns the directory they're in, relative to the extension's work
+/// directory.
+fn install_runtime_typedefs(
+    runtime: &runtime::Runtime,
+    settings: &Settings,
+    warnings: &mut Vec<String>,
+) -> Result<Option<String>> {
+    let installed = || runtime::find_installed(runtime, settings.runtime_version.as_deref());
+    if settings.offline {
+        if installed().is_none() {
+            warnings.push(format!(
+                "offline mode is enabled, but the {} type definitions haven't been downloaded before; skipping them.",
+                runtime.name
+            ));
         }
+        return Ok(installed());
     }
-}
 
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-struct BinarySettings {
-    ignore_system_version: bool,
-    path: Option<String>,
-    args: Vec<String>,
-}
-
-impl Default for BinarySettings {
-    fn default() -> Self {
-        Self {
-            ignore_system_version: false,
-            path: None,
-            args: Default::default(),
-        }
+    match runtime::install(
+        runtime,
+        settings.runtime_version.as_deref(),
+        settings.mirrors.github_api.trim_end_matches('/'),
+    ) {
+        Ok(dir) => Ok(Some(dir)),
+        Err(e) => match installed() {
+            Some(dir) => {
+                warnings.push(format!(
+                    "{e}; using previously downloaded {} type definitions.",
+                    runtime.name
+                ));
+                Ok(Some(dir))
+            }
+            None => Err(e),
+        },
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-struct PluginSettings {
-    enabled: bool,
-    port: u16,
-    proxy_path: Option<String>,
+/// Reports problems that don't prevent the language server from starting. `warnings` are only
+/// logged, while `fallbacks`, which describe features that had to be disabled, are also shown as a
+/// failed status in Zed.
+fn report_warnings(
+    language_server_id: &LanguageServerId,
+    warnings: &[String],
+    fallbacks: &[String],
+) {
+    for warning in warnings.iter().chain(fallbacks) {
+        eprintln!("zed-luau: {warning}");
+    }
+    if !fallbacks.is_empty() {
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::Failed(fallbacks.join("\n")),
+        );
+    }
 }
 
-impl Default for PluginSettings {
-    fn default() -> Self {
-        Self {
-            enabled: false,
-            port: 3667,
-            proxy_path: None,
+/// Makes sure the file `file_name` downloaded from `url` is present, refreshing it if it's older
+/// than `cache.max_age` unless offline mode is enabled. Returns whether the file can be used.
+fn fetch_file(
+    url: &str,
+    file_name: &str,
+    settings: &Settings,
+    warnings: &mut Vec<String>,
+) -> Result<bool> {
+    if settings.offline {
+        if is_file(file_name) {
+            return Ok(true);
         }
+        warnings.push(format!(
+            "offline mode is enabled, but {file_name} hasn't been downloaded before; skipping it."
+        ));
+        return Ok(false);
     }
-}
 
-struct LuauExtension {
-    cached_binary_path: Option<String>,
-    cached_proxy_path: Option<String>,
-}
-
-fn is_file(path: &str) -> bool {
-    fs::metadata(path).map_or(false, |stat| stat.is_file())
+    if let cache::Fetched::Stale(e) = cache::fetch(url, file_name, settings.cache.max_age)? {
+        warnings.push(format!("{e}; using previously downloaded {file_name}."));
+    }
+    Ok(true)
 }
 
-fn is_dir(path: &str) -> bool {
-    fs::metadata(path).map_or(false, |stat| stat.is_dir())
+/// Returns the tool luau-lsp is installed as for `channel`.
+fn luau_lsp_tool(channel: &Channel) -> &'static Tool {
+    match channel {
+        Channel::Stable => &tools::LUAU_LSP,
+        Channel::Prerelease => &tools::LUAU_LSP_PRERELEASE,
+    }
 }
 
-fn get_extension_settings(settings_val: Option<serde_json::Value>) -> Result<Settings> {
-    let Some(mut settings_val) = settings_val else {
-        return Ok(Settings::default());
-    };
-
-    let Some(settings) = settings_val.as_object_mut() else {
-        return Err("invalid luau-lsp settings: `settings` must be an object, but isn't.".into());
+/// Returns the path to the installed luau-lsp binary selected by `binary.use_installed` or
+/// `binary.rollback` among the installs of `binary.channel`, if either is set.
+fn select_installed_luau_lsp(settings: &Settings) -> Result<Option<String>> {
+    let tool = luau_lsp_tool(&settings.binary.channel);
+    let installed = tool.installed_versions();
+    let list_installed = || {
+        let tags: Vec<&str> = installed.iter().map(|v| v.tag.as_str()).collect();
+        match tags.is_empty() {
+            true => "none".to_string(),
+            false => tags.join(", "),
+        }
     };
 
-    let luau_lsp_settings = settings.remove("luau-lsp");
-    let mut value = settings.remove("ext").unwrap_or(settings_val);
-    if let Value::Object(o) = &mut value {
-        o.insert(
-            "luau-lsp".to_string(),
-            luau_lsp_settings.unwrap_or(Value::Object(Map::new())),
-        );
-    }
-
-    let mut result: Result<Settings> =
-        serde_path_to_error::deserialize(value).map_err(|e| e.to_string());
-    if let Ok(ref mut settings) = result {
-        let types = json::get_or_insert_object(&mut settings.luau_lsp, "types");
-        // Merge luau-lsp.types.definitions and definitions. The former is read by the language
-        // server to e.g. not treat definition files as regular luau files. The latter cannot be
-        // removed for backwards compatibility reasons.
-        let definition_files = json::get_or_insert_array(types, "definitionFiles");
-        let old_definition_count = settings.definitions.len();
-        for def in &mut *definition_files {
-            if let Value::String(s) = def {
-                settings.definitions.push(s.clone())
+    let selected = if let Some(tag) = &settings.binary.use_installed {
+        match installed.iter().find(|installed| &installed.tag == tag) {
+            Some(installed) => installed,
+            None => {
+                return Err(format!(
+                    "luau-lsp {tag} selected by `binary.use_installed` isn't installed. Installed versions: {}.",
+                    list_installed()
+                ));
             }
         }
-        for i in 0..old_definition_count {
-            definition_files.push(Value::String(settings.definitions[i].clone()));
+    } else if settings.binary.rollback {
+        match installed.get(1) {
+            Some(installed) => installed,
+            None => {
+                return Err(format!(
+                    "`binary.rollback` is enabled, but there is no previous luau-lsp version to roll back to. Installed versions: {}.",
+                    list_installed()
+                ));
+            }
         }
-    }
-    result
+    } else {
+        return Ok(None);
+    };
+
+    tool.check_installed(&selected.binary_path, settings.binary.sha256.as_deref())?;
+    Ok(Some(selected.binary_path.clone()))
 }
 
-fn download_fflags() -> Result<()> {
-    zed::download_file(
-        FFLAG_URL,
-        FFLAG_FILE_NAME,
-        zed::DownloadedFileType::Uncompressed,
-    )
-    .map_err(|e| format!("failed to download file: {e}"))?;
-    Ok(())
+/// Returns an error if the proxy at release `tag` may not accept the arguments the extension passes.
+fn check_proxy_version(tag: &str) -> Result<()> {
+    let compatible = semver::VersionReq::parse(COMPATIBLE_PROXY_VERSIONS).unwrap();
+    match github::parse_tag_version(tag) {
+        Some(version) if compatible.matches(&version) => Ok(()),
+        _ => Err(format!(
+            "luau-lsp-proxy {tag} isn't supported by this version of the extension, as its command-line interface may differ. Set `plugin.proxy_version` to a version matching \"{COMPATIBLE_PROXY_VERSIONS}\", or set `plugin.proxy_path`."
+        )),
+    }
 }
 
 struct BinaryPath {
@@ -205,6 +241,7 @@ impl LuauExtension {
         language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
         settings: &Settings,
+        warnings: &mut Vec<String>,
     ) -> Result<BinaryPath> {
         if let Some(path) = &settings.binary.path {
             return Ok(BinaryPath {
@@ -213,96 +250,108 @@ impl LuauExtension {
             });
         }
 
-        if !settings.binary.ignore_system_version {
-            if let Some(path) = worktree.which("luau-lsp") {
-                return Ok(BinaryPath {
-                    path,
-                    is_extension_owned: false,
-                });
-            }
+        if !settings.binary.ignore_system_version
+            && let Some(path) = worktree.which("luau-lsp")
+        {
+            return Ok(BinaryPath {
+                path,
+                is_extension_owned: false,
+            });
         }
 
-        if let Some(path) = &self.cached_binary_path {
-            if is_file(path) {
-                return Ok(BinaryPath {
-                    path: path.clone(),
-                    is_extension_owned: true,
-                });
-            }
+        if let Some(path) = select_installed_luau_lsp(settings)? {
+            return Ok(BinaryPath {
+                path,
+                is_extension_owned: true,
+            });
         }
 
-        zed::set_language_server_installation_status(
-            &language_server_id,
-            &zed::LanguageServerInstallationStatus::CheckingForUpdate,
-        );
-        let release = zed::latest_github_release(
-            "JohnnyMorganz/luau-lsp",
-            zed::GithubReleaseOptions {
-                require_assets: true,
-                pre_release: false,
-            },
-        )?;
-
-        let (platform, arch) = zed::current_platform();
-
-        let asset_name = match platform {
-            zed::Os::Mac => "luau-lsp-macos.zip",
-            zed::Os::Windows => "luau-lsp-win64.zip",
-            zed::Os::Linux => match arch {
-                zed::Architecture::Aarch64 => "luau-lsp-linux-arm64.zip",
-                _ => "luau-lsp-linux-x86_64.zip",
-            },
+        // The file the version was pinned in, if it came from a toolchain manager manifest.
+        let mut pinned_in = None;
+        let version = match &settings.binary.version {
+            Some(version) => Some(VersionSpec::parse(version)),
+            None if settings.binary.use_toolchain_manifests => toolchain::find_pinned_version(
+                worktree,
+                &[&settings.mirrors.luau_lsp_repo, DEFAULT_LUAU_LSP_REPO],
+                warnings,
+            )
+            .map(|pinned| {
+                pinned_in = Some(pinned.file_name);
+                pinned.version
+            }),
+            None => None,
         };
-
-        let asset = release
-            .assets
-            .iter()
-            .find(|asset| asset.name == asset_name)
-            .ok_or_else(|| format!("no asset found matching {:?}", asset_name))?;
-
-        let dir_name = format!("luau-lsp-{}", release.version);
-        let version_dir = format!("{LUAU_LSP_BINARY_DIR_NAME}/{dir_name}");
-        let binary_path = format!(
-            "{version_dir}/luau-lsp{}",
-            match platform {
-                zed::Os::Mac | zed::Os::Linux => "",
-                zed::Os::Windows => ".exe",
+        let tool = luau_lsp_tool(&settings.binary.channel);
+        let sha256 = settings.binary.sha256.as_deref();
+
+        match &version {
+            // Installs of a pinned version are looked up on disk so that restarting doesn't
+            // require a network request.
+            Some(version) => {
+                if let Some(path) = tool.find_installed(Some(version))
+                    && tool.check_installed(&path, sha256).is_ok()
+                {
+                    return Ok(BinaryPath {
+                        path,
+                        is_extension_owned: true,
+                    });
+                }
+            }
+            None => {
+                if let Some(path) = &self.cached_binary_path
+                    && path.starts_with(&format!("{}/", tool.install_dir))
+                    && is_file(path)
+                    && tool.check_installed(path, sha256).is_ok()
+                {
+                    return Ok(BinaryPath {
+                        path: path.clone(),
+                        is_extension_owned: true,
+                    });
+                }
             }
-        );
-
-        if !is_dir(LUAU_LSP_BINARY_DIR_NAME) {
-            fs::create_dir(LUAU_LSP_BINARY_DIR_NAME)
-                .map_err(|e| format!("failed to create directory for the luau-lsp binary: {e}"))?;
         }
 
-        if !is_file(&binary_path) {
-            zed::set_language_server_installation_status(
-                &language_server_id,
-                &zed::LanguageServerInstallationStatus::Downloading,
-            );
+        if settings.offline {
+            return match tool.find_installed(version.as_ref()) {
+                Some(path) => {
+                    tool.check_installed(&path, sha256)?;
+                    Ok(BinaryPath {
+                        path,
+                        is_extension_owned: true,
+                    })
+                }
+                None => Err(match &version {
+                    Some(version) => format!(
+                        "offline mode is enabled, but luau-lsp {version} isn't installed. Disable `offline` to install it, or set `binary.path`."
+                    ),
+                    None => "offline mode is enabled, but luau-lsp isn't installed. Disable `offline` to install it, or set `binary.path`.".into(),
+                }),
+            };
+        }
 
-            zed::download_file(
-                &asset.download_url,
-                &version_dir,
-                zed::DownloadedFileType::Zip,
+        let binary_path = tool
+            .install(
+                language_server_id,
+                &InstallOptions {
+                    repo: &settings.mirrors.luau_lsp_repo,
+                    api_url: settings.mirrors.github_api.trim_end_matches('/'),
+                    version: version.as_ref(),
+                    pre_release: settings.binary.channel == Channel::Prerelease,
+                    sha256,
+                    keep_versions: settings.binary.keep_versions,
+                    target: Target::current(&settings.binary.libc, worktree),
+                },
+                warnings,
             )
-            .map_err(|e| format!("failed to download file: {e}"))?;
-
-            zed::make_file_executable(&binary_path)?;
+            .map_err(|e| match pinned_in {
+                Some(file_name) => format!("{e} (pinned in {file_name})"),
+                None => e,
+            })?;
 
-            let entries = fs::read_dir(LUAU_LSP_BINARY_DIR_NAME)
-                .map_err(|e| format!("failed to list luau-lsp binary directory {e}"))?;
-            for entry in entries {
-                let entry = entry
-                    .map_err(|e| format!("failed to load luau-lsp binary directory entry {e}"))?;
-                if entry.file_name().to_str() != Some(&dir_name) {
-                    fs::remove_dir_all(&entry.path()).ok();
-                }
-            }
+        if version.is_none() {
+            self.cached_binary_path = Some(binary_path.clone());
         }
 
-        self.cached_binary_path = Some(binary_path.clone());
-
         Ok(BinaryPath {
             path: binary_path,
             is_extension_owned: true,
@@ -312,118 +361,60 @@ impl LuauExtension {
     fn proxy_binary_path(
         &mut self,
         language_server_id: &LanguageServerId,
+        worktree: &zed::Worktree,
         settings: &Settings,
+        warnings: &mut Vec<String>,
     ) -> Result<String> {
         if let Some(path) = &settings.plugin.proxy_path {
             return Ok(path.clone());
         }
 
-        zed::set_language_server_installation_status(
-            &language_server_id,
-            &zed::LanguageServerInstallationStatus::CheckingForUpdate,
-        );
-
-        // We version pin the proxy so that we don't need to worry about backwards compatibility for it.
-        let release = zed::github_release_by_tag_name("4teapo/luau-lsp-proxy", "v0.1.0")?;
-
-        let (platform, arch) = zed::current_platform();
-
-        let asset_name = format!(
-            "luau-lsp-proxy-{version}-{os}-{arch}.zip",
-            version = {
-                let mut chars = release.version.chars();
-                chars.next();
-                chars.as_str()
-            },
-            os = match platform {
-                zed::Os::Mac => "macos",
-                zed::Os::Windows => "windows",
-                zed::Os::Linux => "linux",
-            },
-            arch = match arch {
-                zed::Architecture::Aarch64 => "aarch64",
-                _ => "x86_64",
-            },
-        );
-
-        let asset = release
-            .assets
-            .iter()
-            .find(|asset| asset.name == asset_name)
-            .ok_or_else(|| format!("no asset found matching {:?}", asset_name))?;
-
-        let dir_name = format!("luau-lsp-proxy-{}", release.version);
-        let version_dir = format!("{PROXY_BINARY_DIR_NAME}/{dir_name}");
-        let binary_path = format!(
-            "{version_dir}/luau-lsp-proxy{}",
-            match platform {
-                zed::Os::Mac | zed::Os::Linux => "",
-                zed::Os::Windows => ".exe",
+        let proxy_version = &settings.plugin.proxy_version;
+        check_proxy_version(proxy_version)?;
+
+        // The proxy version is always an exact tag, so an existing install can be used without
+        // checking for a release.
+        let sha256 = settings.plugin.proxy_sha256.as_deref();
+        let binary_path = tools::LUAU_LSP_PROXY.binary_path(proxy_version);
+        if is_file(&binary_path) {
+            match tools::LUAU_LSP_PROXY.check_installed(&binary_path, sha256) {
+                Ok(()) => return Ok(binary_path),
+                Err(e) if settings.offline => return Err(e),
+                // Installing it again verifies the new archive against the hash.
+                Err(_) => {}
             }
-        );
-
-        if !is_dir(PROXY_BINARY_DIR_NAME) {
-            fs::create_dir(PROXY_BINARY_DIR_NAME)
-                .map_err(|e| format!("failed to create directory for the proxy binary: {e}"))?;
         }
 
-        if !is_file(&binary_path) {
-            zed::set_language_server_installation_status(
-                &language_server_id,
-                &zed::LanguageServerInstallationStatus::Downloading,
-            );
-
-            zed::download_file(
-                &asset.download_url,
-                &version_dir,
-                zed::DownloadedFileType::Zip,
-            )
-            .map_err(|e| format!("failed to download file: {e}"))?;
-
-            zed::make_file_executable(&binary_path)?;
-
-            let entries = fs::read_dir(PROXY_BINARY_DIR_NAME)
-                .map_err(|e| format!("failed to list proxy binary directory {e}"))?;
-            for entry in entries {
-                let entry = entry
-                    .map_err(|e| format!("failed to load proxy binary directory entry {e}"))?;
-                if entry.file_name().to_str() != Some(&dir_name) {
-                    fs::remove_dir_all(&entry.path()).ok();
-                }
-            }
+        if settings.offline {
+            return Err(format!(
+                "offline mode is enabled, but luau-lsp-proxy {proxy_version} isn't installed. Disable `offline` to install it, or set `plugin.proxy_path`."
+            ));
         }
 
-        self.cached_proxy_path = Some(binary_path.clone());
-
-        Ok(binary_path)
+        tools::LUAU_LSP_PROXY.install(
+            language_server_id,
+            &InstallOptions {
+                repo: &settings.mirrors.proxy_repo,
+                api_url: settings.mirrors.github_api.trim_end_matches('/'),
+                version: Some(&VersionSpec::Tag(proxy_version.clone())),
+                pre_release: false,
+                sha256,
+                // The proxy version only changes with `plugin.proxy_version`, so there's nothing
+                // to roll back to.
+                keep_versions: 1,
+                target: Target::current(&settings.binary.libc, worktree),
+            },
+            warnings,
+        )
     }
 }
 
 impl zed::Extension for LuauExtension {
     fn new() -> Self {
-        // Try deleting files for definitions, docs & fflags to make sure they are downloaded again
-        // later, keeping them up to date.
-        fs::remove_file(FFLAG_FILE_NAME).ok();
-        fs::remove_file(roblox::API_DOCS_FILE_NAME).ok();
-        fs::remove_file(roblox::get_definitions_file_for_level(
-            roblox::SECURITY_LEVEL_ROBLOX_SCRIPT,
-        ))
-        .ok();
-        fs::remove_file(roblox::get_definitions_file_for_level(
-            roblox::SECURITY_LEVEL_LOCAL_USER,
-        ))
-        .ok();
-        fs::remove_file(roblox::get_definitions_file_for_level(
-            roblox::SECURITY_LEVEL_PLUGIN,
-        ))
-        .ok();
-        fs::remove_file(roblox::get_definitions_file_for_level(
-            roblox::SECURITY_LEVEL_NONE,
-        ))
-        .ok();
+        // Files for definitions, docs & fflags are kept between sessions and refreshed based on
+        // `cache.max_age`. See `fetch_file`.
         Self {
             cached_binary_path: None,
-            cached_proxy_path: None,
         }
     }
 
@@ -432,44 +423,55 @@ impl zed::Extension for LuauExtension {
         language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
     ) -> Result<zed::Command> {
-        let lsp_settings = match LspSettings::for_worktree(language_server_id.as_ref(), worktree) {
-            Ok(v) => v,
-            Err(e) => return Err(e),
-        };
+        let lsp_settings = LspSettings::for_worktree(language_server_id.as_ref(), worktree)?;
 
-        let settings = get_extension_settings(lsp_settings.settings)?;
+        let (settings, mut warnings) = get_extension_settings(lsp_settings.settings, worktree)?;
+        let mut fallbacks: Vec<String> = Vec::new();
 
-        let binary_path =
-            self.language_server_binary_path(language_server_id, worktree, &settings)?;
+        let binary_path = self.language_server_binary_path(
+            language_server_id,
+            worktree,
+            &settings,
+            &mut warnings,
+        )?;
 
         let current_dir = std::env::current_dir().unwrap();
         let current_dir_str = current_dir.display();
 
-        fn is_path_absolute(path: &str) -> bool {
-            let (platform, _) = zed::current_platform();
-            match platform {
-                // We need to handle Windows manually because of our UNIX-based WASM environment
-                zed::Os::Windows => {
-                    let mut chars = path.chars();
-                    match (chars.next(), chars.next(), chars.next()) {
-                        (Some(drive), Some(':'), Some(sep)) => {
-                            drive.is_ascii_alphabetic() && (sep == '\\' || sep == '/')
-                        }
-                        // UNC path
-                        _ => path.starts_with("//") || path.starts_with("\\\\"),
-                    }
+        // The proxy is only needed for the Studio plugin, so if it can't be set up, luau-lsp is
+        // started on its own rather than not at all.
+        let root_path = worktree.root_path();
+        let mut set_up_proxy = || -> Result<_> {
+            let proxy_path =
+                self.proxy_binary_path(language_server_id, worktree, &settings, &mut warnings)?;
+            let connection = plugin::connection_for(&root_path, &settings.plugin.port)?;
+            Ok((proxy_path, connection))
+        };
+        let proxy = match settings.plugin.enabled {
+            true => match set_up_proxy() {
+                Ok(proxy) => Some(proxy),
+                Err(e) => {
+                    fallbacks.push(format!(
+                        "failed to set up luau-lsp-proxy, so Roblox Studio plugin support is disabled: {e}"
+                    ));
+                    None
                 }
-                _ => Path::new(OsStr::new(path)).is_absolute(),
-            }
-        }
+            },
+            false => None,
+        };
 
         let mut args: Vec<String> = Vec::new();
-        if settings.plugin.enabled {
-            args.push(settings.plugin.port.to_string());
+        if let Some((_, connection)) = &proxy {
+            eprintln!(
+                "zed-luau: the Roblox Studio plugin port for {root_path} is {}, as recorded in {current_dir_str}/{}",
+                connection.port,
+                plugin::CONNECTIONS_FILE_NAME
+            );
+            args.push(connection.port.to_string());
             if binary_path.is_extension_owned {
-                args.push(format!("{}/{}", current_dir_str, binary_path.path.clone()).into());
+                args.push(format!("{}/{}", current_dir_str, binary_path.path.clone()));
             } else {
-                args.push(binary_path.path.clone().into());
+                args.push(binary_path.path.clone());
             }
         }
         args.push("lsp".into());
@@ -480,70 +482,26 @@ impl zed::Extension for LuauExtension {
                 args.push("--no-flags-enabled".into());
             }
 
-            let mut fflags = HashMap::new();
-
-            if settings.fflags.sync {
-                if !is_file(FFLAG_FILE_NAME) {
-                    download_fflags()?;
-                }
-                let as_str = fs::read_to_string(FFLAG_FILE_NAME)
-                    .map_err(|e| format!("failed to read fflags.json: {e}"))?;
-                let json: serde_json::Value = serde_json::from_str(&as_str)
-                    .map_err(|e| format!("failed to parse fflags.json: {e}"))?;
-                let Some(json_map) = json.as_object() else {
-                    return Err("failed to sync fflags: error when parsing fetched fflags: fflags must be an object, but isn't.".into());
-                };
-                let Some(app_settings_val) = json_map.get("applicationSettings") else {
-                    return Err("failed to sync fflags: error when reading parsed fflags: json.applicationSettings must exist, but doesn't.".into());
-                };
-                let Some(app_settings) = app_settings_val.as_object() else {
-                    return Err("failed to sync fflags: error when reading parsed fflags: json.applicationSettings must be an object, but isn't.".into());
-                };
-                for (name, value) in app_settings.iter() {
-                    let Some(value) = value.as_str() else {
-                        return Err("failed to sync fflags: error when reading parsed fflags: all values in json.applicationSettings must be strings, but one or more aren't.".into());
-                    };
-                    for prefix in FFLAG_PREFIXES {
-                        if name.starts_with(&format!("{prefix}Luau")) {
-                            fflags.insert(name[prefix.len()..].into(), value.to_string());
-                            break;
-                        }
-                    }
-                }
-            }
-
-            for (name, value) in settings.fflags.overrides.iter() {
-                if name.len() == 0 || value.len() == 0 {
-                    return Err("failed to apply fflag overrides: all overrides must have a non-empty name and value.".into());
-                }
-                fflags.insert(name.clone(), value.to_string());
-            }
-
-            if settings.fflags.enable_new_solver {
-                fflags.insert("LuauSolverV2".to_string(), "true".to_string());
-                fflags.insert(
-                    "LuauNewSolverPopulateTableLocations".to_string(),
-                    "true".to_string(),
-                );
-                fflags.insert(
-                    "LuauNewSolverPrePopulateClasses".to_string(),
-                    "true".to_string(),
-                );
-            }
-
+            let fflags = fflags::resolve(&settings, worktree, &mut warnings)?;
             for (name, value) in fflags.iter() {
-                args.push(format!("--flag:{}={}", name, value).into());
+                args.push(format!("--flag:{}={}", name, value));
             }
         }
 
         if settings.roblox.enabled {
-            if settings.roblox.download_api_documentation {
-                if !is_file(roblox::API_DOCS_FILE_NAME) {
-                    roblox::download_api_docs()?;
-                }
-                args.push(
-                    format!("--docs={}/{}", &current_dir_str, roblox::API_DOCS_FILE_NAME).into(),
-                );
+            if settings.roblox.download_api_documentation
+                && fetch_file(
+                    &roblox::get_api_docs_url(&settings.mirrors.api_docs),
+                    roblox::API_DOCS_FILE_NAME,
+                    &settings,
+                    &mut warnings,
+                )?
+            {
+                args.push(format!(
+                    "--docs={}/{}",
+                    &current_dir_str,
+                    roblox::API_DOCS_FILE_NAME
+                ));
             }
 
             if settings.roblox.download_definitions {
@@ -556,16 +514,51 @@ impl zed::Extension for LuauExtension {
 
                 let definitions_file_name = roblox::get_definitions_file_for_level(security_level);
 
-                if !is_file(&definitions_file_name) {
-                    roblox::download_definitions(security_level)?;
-                }
-                args.push(
-                    format!(
+                if fetch_file(
+                    &roblox::get_definitions_url_for_level(
+                        &settings.mirrors.definitions,
+                        security_level,
+                    ),
+                    &definitions_file_name,
+                    &settings,
+                    &mut warnings,
+                )? {
+                    args.push(format!(
                         "--definitions:@roblox={}/{}",
                         &current_dir_str, definitions_file_name
-                    )
-                    .into(),
-                );
+                    ));
+                }
+            }
+        }
+
+        if let Some(runtime) = &settings.runtime {
+            let runtime = runtime.typedefs_source();
+            let dir = match &settings.runtime_typedefs {
+                Some(path) => Some(resolve_worktree_path(path, worktree)),
+                None => install_runtime_typedefs(runtime, &settings, &mut warnings)?
+                    .map(|dir| format!("{current_dir_str}/{dir}")),
+            };
+
+            if let Some(dir) = dir {
+                match runtime::definition_files(&dir) {
+                    Ok(files) => {
+                        for file in &files {
+                            let name = match files.len() {
+                                1 => runtime.name.to_string(),
+                                _ => format!(
+                                    "{}-{}",
+                                    runtime.name,
+                                    file.trim_end_matches(".d.luau")
+                                ),
+                            };
+                            args.push(format!("--definitions:@{name}={dir}/{file}"));
+                        }
+                    }
+                    Err(e) => fallbacks.push(format!(
+                        "{e}, so the {} global definition files in it aren't loaded. Add them to `definitions` instead.",
+                        runtime.name
+                    )),
+                }
             }
         }
 
@@ -583,13 +576,13 @@ impl zed::Extension for LuauExtension {
             let proj_root_str = &format!("{}/", worktree.root_path());
 
             for def in &settings.definitions {
-                let prefix = get_prefix(&def, &proj_root_str);
-                args.push(format!("--definitions={prefix}{def}").into());
+                let prefix = get_prefix(def, proj_root_str);
+                args.push(format!("--definitions={prefix}{def}"));
             }
 
             for doc in &settings.documentation {
-                let prefix = get_prefix(&doc, &proj_root_str);
-                args.push(format!("--docs={prefix}{doc}").into());
+                let prefix = get_prefix(doc, proj_root_str);
+                args.push(format!("--docs={prefix}{doc}"));
             }
         }
 
@@ -597,12 +590,13 @@ impl zed::Extension for LuauExtension {
             args.push(arg.into());
         }
 
-        let command = if settings.plugin.enabled {
-            self.proxy_binary_path(language_server_id, &settings)?
-        } else {
-            binary_path.path.clone()
+        let command = match proxy {
+            Some((proxy_path, _)) => proxy_path,
+            None => binary_path.path.clone(),
         };
 
+        report_warnings(language_server_id, &warnings, &fallbacks);
+
         Ok(zed::Command {
             command,
             args,
@@ -643,15 +637,48 @@ impl zed::Extension for LuauExtension {
         }
     }
 
+    fn language_server_additional_workspace_configuration(
+        &mut self,
+        _language_server_id: &LanguageServerId,
+        target_language_server_id: &LanguageServerId,
+        _worktree: &zed::Worktree,
+    ) -> Result<Option<serde_json::Value>> {
+        // Offer completion and validation for the extension settings when editing Zed settings.
+        if target_language_server_id.as_ref() != "json-language-server" {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::json!({
+            "json": {
+                "schemas": settings::json_schemas(),
+            },
+        })))
+    }
+
     fn language_server_workspace_configuration(
         &mut self,
         language_server_id: &zed::LanguageServerId,
-        _worktree: &zed::Worktree,
+        worktree: &zed::Worktree,
     ) -> Result<Option<serde_json::Value>> {
-        let settings_val = LspSettings::for_worktree(language_server_id.as_ref(), _worktree)
+        let settings_val = LspSettings::for_worktree(language_server_id.as_ref(), worktree)
             .ok()
             .and_then(|lsp_settings| lsp_settings.settings.clone());
-        let settings = get_extension_settings(settings_val)?;
+        let (mut settings, _) = get_extension_settings(settings_val, worktree)?;
+
+        if let Some(runtime) = &settings.runtime {
+            let alias = runtime.typedefs_source().alias;
+            if let Some(dir) = get_runtime_typedefs_dir(&settings, worktree) {
+                let require = json::get_or_insert_object(&mut settings.luau_lsp, "require");
+                json::get_or_insert_object(require, "directoryAliases")
+                    .entry(alias)
+                    .or_insert_with(|| Value::String(format!("{}/", dir.trim_end_matches('/'))));
+            }
+            if !settings.roblox.enabled {
+                json::get_or_insert_object(&mut settings.luau_lsp, "platform")
+                    .entry("type")
+                    .or_insert_with(|| Value::String("standard".into()));
+            }
+        }
+
         Ok(Some(serde_json::json!({
             "luau-lsp": settings.luau_lsp
         })))
@@ -0,0 +1,54 @@
+use crate::settings::Libc;
+use std::fmt;
+use zed_extension_api as zed;
+
+/// The platform binaries are installed for.
+#[derive(Clone, Copy)]
+pub struct Target {
+    pub os: zed::Os,
+    pub arch: zed::Architecture,
+    /// Whether the platform uses musl instead of glibc. Only ever true on Linux.
+    pub musl: bool,
+}
+
+impl Target {
+    /// Returns the platform Zed is running on. Extensions can't inspect the system's libc, so
+    /// unless `libc` says otherwise, musl is assumed if Alpine's package manager is on the `$PATH`.
+    pub fn current(libc: &Libc, worktree: &zed::Worktree) -> Self {
+        let (os, arch) = zed::current_platform();
+        let musl = os == zed::Os::Linux
+            && match libc {
+                Libc::Auto => worktree.which("apk").is_some(),
+                Libc::Glibc => false,
+                Libc::Musl => true,
+            };
+        Self { os, arch, musl }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let os = match (self.os, self.musl) {
+            (zed::Os::Mac, _) => "macos",
+            (zed::Os::Windows, _) => "windows",
+            (zed::Os::Linux, false) => "linux",
+            (zed::Os::Linux, true) => "linux-musl",
+        };
+        let arch = match self.arch {
+            zed::Architecture::Aarch64 => "aarch64",
+            zed::Architecture::X86 => "x86",
+            zed::Architecture::X8664 => "x86_64",
+        };
+        write!(f, "{os}/{arch}")
+    }
+}
+
+/// Returns the first asset of `release` named one of `names`.
+pub fn find_asset<'a>(
+    release: &'a zed::GithubRelease,
+    names: &[String],
+) -> Option<&'a zed::GithubReleaseAsset> {
+    names
+        .iter()
+        .find_map(|name| release.assets.iter().find(|asset| &asset.name == name))
+}
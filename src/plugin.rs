@@ -0,0 +1,154 @@
+use crate::settings::Port;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::RangeInclusive;
+use zed_extension_api::{Result, serde_json};
+
+/// The file recording the Studio plugin connection details of each worktree, keyed by worktree
+/// root path.
+pub const CONNECTIONS_FILE_NAME: &str = "plugin-connections.json";
+/// The ports `plugin.port = "auto"` chooses from.
+const AUTO_PORTS: RangeInclusive<u16> = 3667..=3766;
+
+/// How the Studio plugin connects to the proxy of a worktree.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Connection {
+    pub port: u16,
+    /// When the language server was last started for the worktree, in seconds since the Unix
+    /// epoch.
+    pub last_used: u64,
+}
+
+fn read_connections() -> HashMap<String, Connection> {
+    fs::read_to_string(CONNECTIONS_FILE_NAME)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn write_connections(connections: &HashMap<String, Connection>) -> Result<()> {
+    let as_str = serde_json::to_string_pretty(connections)
+        .map_err(|e| format!("failed to serialize plugin connections: {e}"))?;
+    fs::write(CONNECTIONS_FILE_NAME, as_str)
+        .map_err(|e| format!("failed to write {CONNECTIONS_FILE_NAME}: {e}"))
+}
+
+/// Returns the port for the worktree at `root_path`.
+///
+/// With `Port::Auto`, the port previously used by the worktree is kept unless it has been assigned
+/// to another worktree since. Otherwise, the first port not assigned to another worktree is chosen,
+/// or if there is none, the port of the least recently used worktree, whose assignment is removed.
+fn choose_port(connections: &mut HashMap<String, Connection>, root_path: &str, port: &Port) -> u16 {
+    if let Port::Fixed(port) = port {
+        return *port;
+    }
+
+    let is_taken = |port: u16| {
+        connections
+            .iter()
+            .any(|(other_root_path, other)| other_root_path != root_path && other.port == port)
+    };
+    if let Some(previous) = connections.get(root_path)
+        && !is_taken(previous.port)
+    {
+        return previous.port;
+    }
+    if let Some(port) = AUTO_PORTS.clone().find(|&port| !is_taken(port)) {
+        return port;
+    }
+
+    // Every port in `AUTO_PORTS` is assigned to another worktree, so at least one of them is in
+    // range.
+    let (least_recently_used, _) = connections
+        .iter()
+        .filter(|(other_root_path, other)| {
+            *other_root_path != root_path && AUTO_PORTS.contains(&other.port)
+        })
+        .min_by_key(|(_, other)| other.last_used)
+        .map(|(other_root_path, other)| (other_root_path.clone(), other.port))
+        .unwrap();
+    connections.remove(&least_recently_used).unwrap().port
+}
+
+/// Returns the Studio plugin connection details for the worktree at `root_path` and records them,
+/// along with the time they were used. See `choose_port` for how ports are chosen.
+pub fn connection_for(root_path: &str, port: &Port) -> Result<Connection> {
+    let mut connections = read_connections();
+    let port = choose_port(&mut connections, root_path, port);
+    let connection = Connection {
+        port,
+        last_used: crate::cache::now(),
+    };
+    connections.insert(root_path.to_string(), connection.clone());
+    write_connections(&connections)?;
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Auto;
+
+    fn connections(entries: &[(&str, u16, u64)]) -> HashMap<String, Connection> {
+        entries
+            .iter()
+            .map(|&(root_path, port, last_used)| {
+                let connection = Connection { port, last_used };
+                (root_path.to_string(), connection)
+            })
+            .collect()
+    }
+
+    const AUTO: Port = Port::Auto(Auto::Auto);
+
+    #[test]
+    fn fixed_ports_are_used_as_is() {
+        let mut connections = connections(&[("/a", 4000, 0)]);
+        assert_eq!(
+            choose_port(&mut connections, "/b", &Port::Fixed(4000)),
+            4000
+        );
+        assert_eq!(connections.len(), 1);
+    }
+
+    #[test]
+    fn auto_keeps_the_previous_port() {
+        let mut connections = connections(&[("/a", 3667, 0), ("/b", 3670, 0)]);
+        assert_eq!(choose_port(&mut connections, "/b", &AUTO), 3670);
+    }
+
+    #[test]
+    fn auto_chooses_the_first_unassigned_port() {
+        let mut connections = connections(&[("/a", 3667, 0), ("/b", 3668, 0)]);
+        assert_eq!(choose_port(&mut connections, "/c", &AUTO), 3669);
+    }
+
+    #[test]
+    fn auto_moves_off_a_port_assigned_to_another_worktree() {
+        let mut connections = connections(&[("/a", 3667, 0), ("/b", 3667, 0)]);
+        assert_eq!(choose_port(&mut connections, "/b", &AUTO), 3668);
+    }
+
+    #[test]
+    fn auto_takes_over_the_least_recently_used_port_when_full() {
+        let root_paths: Vec<String> = AUTO_PORTS.map(|port| format!("/{port}")).collect();
+        let entries: Vec<(&str, u16, u64)> = root_paths
+            .iter()
+            .zip(AUTO_PORTS)
+            .map(|(root_path, port)| {
+                let last_used = match port {
+                    3700 => 5,
+                    _ => 1000 + u64::from(port),
+                };
+                (root_path.as_str(), port, last_used)
+            })
+            .collect();
+        let mut connections = connections(&entries);
+
+        assert_eq!(choose_port(&mut connections, "/new", &AUTO), 3700);
+        assert!(!connections.contains_key("/3700"));
+        assert_eq!(connections.len(), AUTO_PORTS.count() - 1);
+    }
+}
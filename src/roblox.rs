@@ -1,15 +1,19 @@
-use zed_extension_api::{self as zed, Result};
-
-const API_DOCS_URL: &str = "https://luau-lsp.pages.dev/api-docs/en-us.json";
+pub const DEFAULT_API_DOCS_BASE_URL: &str = "https://luau-lsp.pages.dev";
+pub const DEFAULT_DEFINITIONS_BASE_URL: &str = "https://luau-lsp.pages.dev";
 pub const SECURITY_LEVEL_NONE: &str = "None";
 pub const SECURITY_LEVEL_LOCAL_USER: &str = "LocalUserSecurity";
 pub const SECURITY_LEVEL_PLUGIN: &str = "PluginSecurity";
 pub const SECURITY_LEVEL_ROBLOX_SCRIPT: &str = "RobloxScriptSecurity";
 pub const API_DOCS_FILE_NAME: &str = "api-docs.json";
 
-pub fn get_definitions_url_for_level(level: &str) -> String {
+pub fn get_api_docs_url(base_url: &str) -> String {
+    format!("{}/api-docs/en-us.json", base_url.trim_end_matches('/'))
+}
+
+pub fn get_definitions_url_for_level(base_url: &str, level: &str) -> String {
     format!(
-        "https://luau-lsp.pages.dev/type-definitions/globalTypes.{}.d.luau",
+        "{}/type-definitions/globalTypes.{}.d.luau",
+        base_url.trim_end_matches('/'),
         level
     )
 }
@@ -17,22 +21,3 @@ pub fn get_definitions_url_for_level(level: &str) -> String {
 pub fn get_definitions_file_for_level(level: &str) -> String {
     format!("globalTypes.{}.d.luau", level)
 }
-
-pub fn download_api_docs() -> Result<()> {
-    zed::download_file(
-        API_DOCS_URL,
-        API_DOCS_FILE_NAME,
-        zed::DownloadedFileType::Uncompressed,
-    )?;
-    Ok(())
-}
-
-pub fn download_definitions(security_level: &str) -> Result<()> {
-    let url = get_definitions_url_for_level(security_level);
-    zed::download_file(
-        &url,
-        &get_definitions_file_for_level(security_level),
-        zed::DownloadedFileType::Uncompressed,
-    )?;
-    Ok(())
-}
@@ -0,0 +1,129 @@
+use crate::is_dir;
+use serde::Deserialize;
+use std::fs;
+use zed_extension_api::Result;
+
+pub const RUNTIMES_DIR_NAME: &str = "runtimes";
+
+/// A non-Roblox Luau runtime whose type definitions can be installed by the extension.
+pub struct Runtime {
+    pub name: &'static str,
+    pub repo: &'static str,
+    /// The directory in `repo` that contains the runtime's type definitions.
+    pub typedefs_dir: &'static str,
+    /// The require alias the runtime's libraries are available under, e.g. `@lune/fs`.
+    pub alias: &'static str,
+}
+
+pub const LUNE: Runtime = Runtime {
+    name: "lune",
+    repo: "lune-org/lune",
+    typedefs_dir: "types",
+    alias: "@lune/",
+};
+
+pub const ZUNE: Runtime = Runtime {
+    name: "zune",
+    repo: "Scythe-Technology/zune",
+    typedefs_dir: "typedefs",
+    alias: "@zune/",
+};
+
+pub const LUTE: Runtime = Runtime {
+    name: "lute",
+    repo: "luau-lang/lute",
+    typedefs_dir: "definitions",
+    alias: "@lute/",
+};
+
+#[derive(Deserialize)]
+struct ContentsEntry {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    download_url: Option<String>,
+}
+
+/// Returns the directory of the installed typedefs for `runtime` at release `tag`, or of the newest
+/// installed typedefs if `tag` is `None`.
+pub fn find_installed(runtime: &Runtime, tag: Option<&str>) -> Option<String> {
+    if let Some(tag) = tag {
+        let dir = format!("{RUNTIMES_DIR_NAME}/{}-{tag}", runtime.name);
+        return is_dir(&dir).then_some(dir);
+    }
+    let prefix = format!("{}-", runtime.name);
+    fs::read_dir(RUNTIMES_DIR_NAME)
+        .ok()?
+        .filter_map(|entry| {
+            let dir_name = entry.ok()?.file_name().into_string().ok()?;
+            let tag = dir_name.strip_prefix(&prefix)?;
+            if tag.ends_with(".download") {
+                return None;
+            }
+            Some((
+                crate::github::parse_tag_version(tag),
+                format!("{RUNTIMES_DIR_NAME}/{dir_name}"),
+            ))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, dir)| dir)
+}
+
+/// Installs the typedefs of `runtime` at release `tag` (the latest release if `None`) and returns
+/// the directory they were installed in.
+pub fn install(runtime: &Runtime, tag: Option<&str>, api_url: &str) -> Result<String> {
+    let tag = match tag {
+        Some(tag) => tag.to_string(),
+        None => crate::github::latest_release(api_url, runtime.repo, false, false)?.version,
+    };
+
+    let dir = format!("{RUNTIMES_DIR_NAME}/{}-{tag}", runtime.name);
+    if is_dir(&dir) {
+        return Ok(dir);
+    }
+
+    let entries: Vec<ContentsEntry> = crate::github::get(
+        api_url,
+        &format!(
+            "/repos/{}/contents/{}?ref={tag}",
+            runtime.repo, runtime.typedefs_dir
+        ),
+        &format!("{} type definition list", runtime.name),
+    )?;
+
+    // Download into a temporary directory so that a failed download doesn't leave behind an
+    // incomplete set of type definitions.
+    let download_dir = format!("{dir}.download");
+    fs::remove_dir_all(&download_dir).ok();
+    fs::create_dir_all(&download_dir)
+        .map_err(|e| format!("failed to create directory {download_dir}: {e}"))?;
+    let result = entries
+        .iter()
+        .filter(|entry| entry.kind == "file" && entry.name.ends_with(".luau"))
+        .try_for_each(|entry| {
+            let Some(url) = &entry.download_url else {
+                return Ok(());
+            };
+            crate::download::download_file(url, &format!("{download_dir}/{}", entry.name))
+                .map_err(|e| format!("failed to download {}: {e}", entry.name))
+        })
+        .and_then(|_| {
+            fs::rename(&download_dir, &dir)
+                .map_err(|e| format!("failed to move {download_dir} to {dir}: {e}"))
+        });
+    if result.is_err() {
+        fs::remove_dir_all(&download_dir).ok();
+    }
+    result.map(|_| dir)
+}
+
+/// Returns the names of the global definition files (`*.d.luau`) in `dir`.
+pub fn definition_files(dir: &str) -> Result<Vec<String>> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("failed to list {dir}: {e}"))?;
+    let mut files: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".d.luau"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,406 @@
+use crate::suggest;
+use zed::serde_json::{Map, Value, json};
+use zed_extension_api as zed;
+
+/// A description of the shape of a settings value.
+pub enum Schema {
+    /// Any value, e.g. settings that are passed through to luau-lsp.
+    Any,
+    Bool,
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    String,
+    Enum(&'static [&'static str]),
+    Nullable(Box<Schema>),
+    Array(Box<Schema>),
+    /// An object with arbitrary keys whose values all have the same shape.
+    Map(Box<Schema>),
+    Object(Vec<Field>),
+    /// A value matching any of the schemas.
+    AnyOf(Vec<Schema>),
+}
+
+pub struct Field {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub schema: Schema,
+}
+
+pub fn field(name: &'static str, description: &'static str, schema: Schema) -> Field {
+    Field {
+        name,
+        description,
+        schema,
+    }
+}
+
+pub fn nullable(schema: Schema) -> Schema {
+    Schema::Nullable(Box::new(schema))
+}
+
+pub fn array(schema: Schema) -> Schema {
+    Schema::Array(Box::new(schema))
+}
+
+pub fn map(schema: Schema) -> Schema {
+    Schema::Map(Box::new(schema))
+}
+
+/// Problems found when validating settings.
+#[derive(Default)]
+pub struct Problems {
+    /// Problems that make the settings unusable.
+    pub errors: Vec<String>,
+    /// Problems that are likely mistakes, like unknown keys, but don't prevent the settings from
+    /// being used.
+    pub warnings: Vec<String>,
+}
+
+fn kind_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Returns a description of the values matching `schema`, used in messages.
+fn describe(schema: &Schema) -> String {
+    match schema {
+        Schema::Any => "any value".into(),
+        Schema::Bool => "a boolean".into(),
+        Schema::Integer { min, max } => format!("an integer from {min} to {max}"),
+        Schema::String => "a string".into(),
+        Schema::Enum(variants) => {
+            let variants: Vec<String> = variants.iter().map(|v| format!("\"{v}\"")).collect();
+            format!("one of {}", variants.join(", "))
+        }
+        Schema::Nullable(schema) => format!("{} or null", describe(schema)),
+        Schema::Array(_) => "an array".into(),
+        Schema::Map(_) | Schema::Object(_) => "an object".into(),
+        Schema::AnyOf(schemas) => {
+            let descriptions: Vec<String> = schemas.iter().map(describe).collect();
+            descriptions.join(" or ")
+        }
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    match path {
+        "" => key.to_string(),
+        _ => format!("{path}.{key}"),
+    }
+}
+
+/// Validates `value` against `schema`, collecting every problem instead of stopping at the first
+/// one. `path` is the path of `value` in the settings, used in messages.
+pub fn validate(value: &Value, schema: &Schema, path: &str, problems: &mut Problems) {
+    let mut expected = || {
+        problems.errors.push(format!(
+            "{}: expected {}, but got {}.",
+            match path {
+                "" => "settings".to_string(),
+                _ => format!("`{path}`"),
+            },
+            describe(schema),
+            match value {
+                Value::String(s) => format!("\"{s}\""),
+                Value::Number(n) => n.to_string(),
+                _ => kind_of(value).to_string(),
+            }
+        ))
+    };
+
+    match schema {
+        Schema::Any => {}
+        Schema::Bool => {
+            if !value.is_boolean() {
+                expected();
+            }
+        }
+        Schema::Integer { min, max } => {
+            if !value.as_i64().is_some_and(|n| (*min..=*max).contains(&n)) {
+                expected();
+            }
+        }
+        Schema::String => {
+            if !value.is_string() {
+                expected();
+            }
+        }
+        Schema::Enum(variants) => {
+            if !value.as_str().is_some_and(|s| variants.contains(&s)) {
+                expected();
+            }
+        }
+        Schema::Nullable(schema) => match (&**schema, value) {
+            (_, Value::Null) => {}
+            // Problems inside arrays and objects are reported at their own path.
+            (Schema::Array(_), Value::Array(_))
+            | (Schema::Map(_) | Schema::Object(_), Value::Object(_)) => {
+                validate(value, schema, path, problems)
+            }
+            _ => {
+                let mut inner = Problems::default();
+                validate(value, schema, path, &mut inner);
+                if !inner.errors.is_empty() {
+                    expected();
+                }
+                problems.warnings.extend(inner.warnings);
+            }
+        },
+        Schema::Array(schema) => match value.as_array() {
+            Some(array) => {
+                for (i, element) in array.iter().enumerate() {
+                    validate(element, schema, &format!("{path}[{i}]"), problems);
+                }
+            }
+            None => expected(),
+        },
+        Schema::Map(schema) => match value.as_object() {
+            Some(object) => {
+                for (key, value) in object {
+                    validate(value, schema, &join_path(path, key), problems);
+                }
+            }
+            None => expected(),
+        },
+        Schema::Object(fields) => match value.as_object() {
+            Some(object) => {
+                for (key, value) in object {
+                    match fields.iter().find(|field| field.name == key) {
+                        Some(field) => {
+                            validate(value, &field.schema, &join_path(path, key), problems)
+                        }
+                        None => {
+                            let mut warning =
+                                format!("unknown setting `{}`.", join_path(path, key));
+                            if let Some(name) =
+                                suggest::closest_match(key, fields.iter().map(|field| field.name))
+                            {
+                                warning.push_str(&format!(" Did you mean `{name}`?"));
+                            }
+                            problems.warnings.push(warning);
+                        }
+                    }
+                }
+            }
+            None => expected(),
+        },
+        Schema::AnyOf(schemas) => {
+            let matching = schemas.iter().find_map(|schema| {
+                let mut alternative = Problems::default();
+                validate(value, schema, path, &mut alternative);
+                alternative.errors.is_empty().then_some(alternative)
+            });
+            match matching {
+                Some(alternative) => problems.warnings.extend(alternative.warnings),
+                None => expected(),
+            }
+        }
+    }
+}
+
+/// Converts `schema` to a JSON schema. `defaults` is the default value described by `schema`, from
+/// which the defaults of object fields are taken.
+pub fn to_json_schema(schema: &Schema, defaults: &Value) -> Value {
+    match schema {
+        Schema::Any => json!({}),
+        Schema::Bool => json!({ "type": "boolean" }),
+        Schema::Integer { min, max } => json!({
+            "type": "integer",
+            "minimum": min,
+            "maximum": max,
+        }),
+        Schema::String => json!({ "type": "string" }),
+        Schema::Enum(variants) => json!({ "type": "string", "enum": variants }),
+        Schema::Nullable(schema) => json!({
+            "anyOf": [to_json_schema(schema, defaults), { "type": "null" }],
+        }),
+        Schema::Array(schema) => json!({
+            "type": "array",
+            "items": to_json_schema(schema, &Value::Null),
+        }),
+        Schema::Map(schema) => json!({
+            "type": "object",
+            "additionalProperties": to_json_schema(schema, &Value::Null),
+        }),
+        Schema::AnyOf(schemas) => json!({
+            "anyOf": schemas
+                .iter()
+                .map(|schema| to_json_schema(schema, defaults))
+                .collect::<Vec<_>>(),
+        }),
+        Schema::Object(fields) => {
+            let mut properties = Map::new();
+            for field in fields {
+                let default = defaults.get(field.name).unwrap_or(&Value::Null);
+                let mut property = to_json_schema(&field.schema, default);
+                if let Value::Object(property) = &mut property {
+                    property.insert("description".into(), field.description.into());
+                    if defaults.get(field.name).is_some() {
+                        property.insert("default".into(), default.clone());
+                    }
+                }
+                properties.insert(field.name.into(), property);
+            }
+            json!({
+                "type": "object",
+                "properties": properties,
+                "additionalProperties": false,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(value: Value, schema: &Schema) -> Problems {
+        let mut problems = Problems::default();
+        validate(&value, schema, "", &mut problems);
+        problems
+    }
+
+    fn settings_schema() -> Schema {
+        Schema::Object(vec![
+            field("enabled", "", Schema::Bool),
+            field("port", "", Schema::Integer { min: 0, max: 65535 }),
+            field("channel", "", Schema::Enum(&["stable", "prerelease"])),
+            field("path", "", nullable(Schema::String)),
+            field("args", "", array(Schema::String)),
+            field("override", "", map(Schema::String)),
+            field(
+                "binary",
+                "",
+                Schema::Object(vec![field("version", "", nullable(Schema::String))]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn accepts_valid_settings() {
+        let problems = check(
+            json!({
+                "enabled": true,
+                "port": 3667,
+                "channel": "prerelease",
+                "path": null,
+                "args": ["--foo"],
+                "override": { "LuauFoo": "true" },
+                "binary": { "version": "^1.40" },
+            }),
+            &settings_schema(),
+        );
+        assert!(problems.errors.is_empty(), "{:?}", problems.errors);
+        assert!(problems.warnings.is_empty(), "{:?}", problems.warnings);
+    }
+
+    #[test]
+    fn reports_every_error_with_its_path() {
+        let problems = check(
+            json!({
+                "enabled": "yes",
+                "port": 70000,
+                "channel": "beta",
+                "args": ["--foo", 1],
+                "override": { "LuauFoo": true },
+                "binary": { "version": 1.4 },
+            }),
+            &settings_schema(),
+        );
+        assert_eq!(
+            problems.errors,
+            [
+                "`args[1]`: expected a string, but got 1.",
+                "`binary.version`: expected a string or null, but got 1.4.",
+                "`channel`: expected one of \"stable\", \"prerelease\", but got \"beta\".",
+                "`enabled`: expected a boolean, but got \"yes\".",
+                "`override.LuauFoo`: expected a string, but got a boolean.",
+                "`port`: expected an integer from 0 to 65535, but got 70000.",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_non_objects_as_settings() {
+        let problems = check(json!([]), &settings_schema());
+        assert_eq!(
+            problems.errors,
+            ["settings: expected an object, but got an array."]
+        );
+    }
+
+    #[test]
+    fn reports_problems_inside_nullable_objects_at_their_path() {
+        let schema = Schema::Object(vec![field(
+            "source",
+            "",
+            nullable(Schema::Object(vec![field("channel", "", Schema::String)])),
+        )]);
+        assert!(check(json!({ "source": null }), &schema).errors.is_empty());
+        assert_eq!(
+            check(json!({ "source": { "channel": 1 } }), &schema).errors,
+            ["`source.channel`: expected a string, but got 1."]
+        );
+        assert_eq!(
+            check(json!({ "source": "beta" }), &schema).errors,
+            ["`source`: expected an object or null, but got \"beta\"."]
+        );
+    }
+
+    #[test]
+    fn warns_about_unknown_keys_with_suggestions() {
+        let problems = check(
+            json!({ "enabeld": true, "binary": { "verison": null }, "something": 1 }),
+            &settings_schema(),
+        );
+        assert!(problems.errors.is_empty(), "{:?}", problems.errors);
+        assert_eq!(
+            problems.warnings,
+            [
+                "unknown setting `binary.verison`. Did you mean `version`?",
+                "unknown setting `enabeld`. Did you mean `enabled`?",
+                "unknown setting `something`.",
+            ]
+        );
+    }
+
+    #[test]
+    fn any_of_accepts_any_alternative() {
+        let schema = Schema::AnyOf(vec![Schema::Bool, Schema::Enum(&["auto"])]);
+        assert!(check(json!(true), &schema).errors.is_empty());
+        assert!(check(json!("auto"), &schema).errors.is_empty());
+    }
+
+    #[test]
+    fn any_of_describes_every_alternative() {
+        let schema = Schema::AnyOf(vec![
+            Schema::Integer { min: 0, max: 65535 },
+            Schema::Enum(&["auto"]),
+        ]);
+        assert_eq!(
+            check(json!("automatic"), &schema).errors,
+            [
+                "settings: expected an integer from 0 to 65535 or one of \"auto\", but got \"automatic\"."
+            ]
+        );
+    }
+
+    #[test]
+    fn any_of_keeps_warnings_of_the_matching_alternative() {
+        let schema = Schema::AnyOf(vec![
+            Schema::Bool,
+            Schema::Object(vec![field("enabled", "", Schema::Bool)]),
+        ]);
+        let problems = check(json!({ "enabled": true, "extra": 1 }), &schema);
+        assert!(problems.errors.is_empty(), "{:?}", problems.errors);
+        assert_eq!(problems.warnings, ["unknown setting `extra`."]);
+    }
+}
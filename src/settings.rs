@@ -0,0 +1,723 @@
+use crate::schema::{self, Schema, array, field, map, nullable};
+use crate::{json, roblox, runtime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use zed::serde_json::{self, Map, Value};
+use zed_extension_api::{self as zed, Result};
+
+const PROJECT_SETTINGS_FILE_NAME: &str = ".zed-luau.json";
+/// Settings that can't be set in project settings files, because they would allow opening a
+/// project to run arbitrary executables.
+const PROJECT_SETTINGS_DISALLOWED: &[&[&str]] =
+    &[&["binary", "path"], &["plugin", "proxy_path"], &["mirrors"]];
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    #[serde(rename = "luau-lsp")]
+    pub luau_lsp: serde_json::Map<String, serde_json::Value>,
+    pub roblox: RobloxSettings,
+    pub fflags: FFlagsSettings,
+    pub binary: BinarySettings,
+    pub plugin: PluginSettings,
+    pub definitions: Vec<String>,
+    pub documentation: Vec<String>,
+    pub offline: bool,
+    pub cache: CacheSettings,
+    pub mirrors: MirrorSettings,
+    pub runtime: Option<Runtime>,
+    pub runtime_version: Option<String>,
+    pub runtime_typedefs: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RobloxSettings {
+    pub enabled: bool,
+    pub security_level: SecurityLevel,
+    pub download_api_documentation: bool,
+    pub download_definitions: bool,
+}
+
+impl Default for RobloxSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            security_level: SecurityLevel::Plugin,
+            download_api_documentation: true,
+            download_definitions: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityLevel {
+    RobloxScript,
+    LocalUser,
+    Plugin,
+    None,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Runtime {
+    Lune,
+    Zune,
+    Lute,
+}
+
+impl Runtime {
+    pub fn typedefs_source(&self) -> &'static runtime::Runtime {
+        match self {
+            Runtime::Lune => &runtime::LUNE,
+            Runtime::Zune => &runtime::ZUNE,
+            Runtime::Lute => &runtime::LUTE,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FFlagsSettings {
+    pub enable_by_default: bool,
+    pub enable_new_solver: NewSolver,
+    pub sync: bool,
+    pub source: FFlagSource,
+    pub snapshot: Option<String>,
+    pub presets: Vec<String>,
+    pub custom_presets: HashMap<String, HashMap<String, String>>,
+    #[serde(rename = "override")]
+    pub overrides: HashMap<String, String>,
+}
+
+impl Default for FFlagsSettings {
+    fn default() -> Self {
+        Self {
+            enable_by_default: false,
+            enable_new_solver: NewSolver::Enabled(false),
+            sync: true,
+            source: Default::default(),
+            snapshot: None,
+            presets: Default::default(),
+            custom_presets: Default::default(),
+            overrides: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FFlagSource {
+    pub application: String,
+    pub channel: Option<String>,
+}
+
+impl Default for FFlagSource {
+    fn default() -> Self {
+        Self {
+            application: "PCDesktopClient".into(),
+            channel: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BinarySettings {
+    pub ignore_system_version: bool,
+    pub path: Option<String>,
+    pub args: Vec<String>,
+    pub version: Option<String>,
+    pub sha256: Option<String>,
+    pub keep_versions: usize,
+    pub use_installed: Option<String>,
+    pub rollback: bool,
+    pub channel: Channel,
+    pub use_toolchain_manifests: bool,
+    pub libc: Libc,
+}
+
+impl Default for BinarySettings {
+    fn default() -> Self {
+        Self {
+            ignore_system_version: false,
+            path: None,
+            args: Default::default(),
+            version: None,
+            sha256: None,
+            keep_versions: 3,
+            use_installed: None,
+            rollback: false,
+            channel: Channel::Stable,
+            use_toolchain_manifests: true,
+            libc: Libc::Auto,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Libc {
+    Auto,
+    Glibc,
+    Musl,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Stable,
+    Prerelease,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    pub max_age: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            // One day.
+            max_age: 60 * 60 * 24,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MirrorSettings {
+    pub fflags: String,
+    pub api_docs: String,
+    pub definitions: String,
+    pub github_api: String,
+    pub luau_lsp_repo: String,
+    pub proxy_repo: String,
+}
+
+impl Default for MirrorSettings {
+    fn default() -> Self {
+        Self {
+            fflags: crate::DEFAULT_FFLAG_BASE_URL.into(),
+            api_docs: roblox::DEFAULT_API_DOCS_BASE_URL.into(),
+            definitions: roblox::DEFAULT_DEFINITIONS_BASE_URL.into(),
+            github_api: crate::github::DEFAULT_API_URL.into(),
+            luau_lsp_repo: crate::DEFAULT_LUAU_LSP_REPO.into(),
+            proxy_repo: crate::DEFAULT_PROXY_REPO.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PluginSettings {
+    pub enabled: bool,
+    pub port: Port,
+    pub proxy_path: Option<String>,
+    pub proxy_version: String,
+    pub proxy_sha256: Option<String>,
+}
+
+impl Default for PluginSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: Port::Fixed(3667),
+            proxy_path: None,
+            proxy_version: crate::PROXY_VERSION.into(),
+            proxy_sha256: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Port {
+    Fixed(u16),
+    Auto(Auto),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum NewSolver {
+    Enabled(bool),
+    /// Mirror Roblox's rollout of the new solver, as reported by the synced `LuauSolverV2` fflag.
+    Auto(Auto),
+}
+
+/// The `"auto"` value of settings that can be chosen automatically.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Auto {
+    Auto,
+}
+
+const CHANNELS: &[&str] = &["stable", "prerelease"];
+const LIBCS: &[&str] = &["auto", "glibc", "musl"];
+
+/// Returns the schema of the settings, which must have a field for every field of `Settings`. This
+/// is checked by `tests::schema_matches_settings`.
+fn settings_schema() -> Schema {
+    Schema::Object(vec![
+        field(
+            "luau-lsp",
+            "luau-lsp settings, as specified by its `ClientConfiguration`.",
+            map(Schema::Any),
+        ),
+        field(
+            "roblox",
+            "Roblox-specific settings.",
+            Schema::Object(vec![
+                field(
+                    "enabled",
+                    "Whether or not Roblox-specific features should be enabled.",
+                    Schema::Bool,
+                ),
+                field(
+                    "security_level",
+                    "The security level of scripts.",
+                    Schema::Enum(&["roblox_script", "local_user", "plugin", "none"]),
+                ),
+                field(
+                    "download_api_documentation",
+                    "Whether or not API documentation should be downloaded and added to luau-lsp.",
+                    Schema::Bool,
+                ),
+                field(
+                    "download_definitions",
+                    "Whether or not definitions should be downloaded and added to luau-lsp.",
+                    Schema::Bool,
+                ),
+            ]),
+        ),
+        field(
+            "fflags",
+            "Settings for the FFlags passed to luau-lsp.",
+            Schema::Object(vec![
+                field(
+                    "enable_by_default",
+                    "Whether or not all boolean, non-experimental fflags should be enabled by default.",
+                    Schema::Bool,
+                ),
+                field(
+                    "enable_new_solver",
+                    "Whether or not the new Luau type solver should be enabled, or \"auto\" to follow the synced `LuauSolverV2` FFlag. Enabling it is the same as enabling the \"new_solver\" preset.",
+                    Schema::AnyOf(vec![Schema::Bool, Schema::Enum(&["auto"])]),
+                ),
+                field(
+                    "sync",
+                    "Whether or not FFlag values should be synced with Roblox's default FFlag values.",
+                    Schema::Bool,
+                ),
+                field(
+                    "source",
+                    "Where synced FFlag values are fetched from.",
+                    Schema::Object(vec![
+                        field(
+                            "application",
+                            "The Roblox application to fetch FFlags for, e.g. \"PCDesktopClient\" or \"PCStudioApp\".",
+                            Schema::String,
+                        ),
+                        field(
+                            "channel",
+                            "The release channel to fetch FFlags for, or null for the production channel.",
+                            nullable(Schema::String),
+                        ),
+                    ]),
+                ),
+                field(
+                    "snapshot",
+                    "A file in the project to use FFlag values from instead of syncing them, relative to the project root.",
+                    nullable(Schema::String),
+                ),
+                field(
+                    "presets",
+                    "The names of the FFlag presets to enable, such as \"new_solver\". Later presets take precedence.",
+                    array(Schema::String),
+                ),
+                field(
+                    "custom_presets",
+                    "FFlag presets by name, which replace built-in presets with the same name.",
+                    map(map(Schema::String)),
+                ),
+                field(
+                    "override",
+                    "FFlags that are forced to some value.",
+                    map(Schema::String),
+                ),
+            ]),
+        ),
+        field(
+            "binary",
+            "Settings for the luau-lsp binary.",
+            Schema::Object(vec![
+                field(
+                    "ignore_system_version",
+                    "Whether or not the extension should skip searching for a binary in your `$PATH` to use instead of installing one itself.",
+                    Schema::Bool,
+                ),
+                field(
+                    "path",
+                    "The path to the language server binary you want to force the extension to use.",
+                    nullable(Schema::String),
+                ),
+                field(
+                    "args",
+                    "Additional arguments to pass to the language server.",
+                    array(Schema::String),
+                ),
+                field(
+                    "version",
+                    "The luau-lsp version to install, either an exact release tag or a semver range. If null, the latest release is used.",
+                    nullable(Schema::String),
+                ),
+                field(
+                    "sha256",
+                    "The SHA-256 hash the downloaded luau-lsp archive must have.",
+                    nullable(Schema::String),
+                ),
+                field(
+                    "keep_versions",
+                    "How many luau-lsp versions to keep installed, including the one in use.",
+                    Schema::Integer {
+                        min: 1,
+                        max: i64::MAX,
+                    },
+                ),
+                field(
+                    "use_installed",
+                    "An installed luau-lsp version to use instead of installing one.",
+                    nullable(Schema::String),
+                ),
+                field(
+                    "rollback",
+                    "Whether or not to use the installed luau-lsp version before the newest one.",
+                    Schema::Bool,
+                ),
+                field(
+                    "channel",
+                    "Whether to install stable releases or pre-releases of luau-lsp.",
+                    Schema::Enum(CHANNELS),
+                ),
+                field(
+                    "use_toolchain_manifests",
+                    "Whether or not to install the luau-lsp version pinned in rokit.toml, aftman.toml or foreman.toml if `version` isn't set.",
+                    Schema::Bool,
+                ),
+                field(
+                    "libc",
+                    "The libc to install luau-lsp and luau-lsp-proxy builds for on Linux.",
+                    Schema::Enum(LIBCS),
+                ),
+            ]),
+        ),
+        field(
+            "plugin",
+            "Settings for Roblox Studio Plugin support.",
+            Schema::Object(vec![
+                field(
+                    "enabled",
+                    "Whether or not Roblox Studio Plugin support should be enabled.",
+                    Schema::Bool,
+                ),
+                field(
+                    "port",
+                    "The port number to connect the Roblox Studio Plugin to, or \"auto\" to choose a free one per project.",
+                    Schema::AnyOf(vec![
+                        Schema::Integer {
+                            min: 0,
+                            max: u16::MAX.into(),
+                        },
+                        Schema::Enum(&["auto"]),
+                    ]),
+                ),
+                field(
+                    "proxy_path",
+                    "The path to the luau-lsp-proxy binary you want to force the extension to use.",
+                    nullable(Schema::String),
+                ),
+                field(
+                    "proxy_version",
+                    "The luau-lsp-proxy release tag the extension should install.",
+                    Schema::String,
+                ),
+                field(
+                    "proxy_sha256",
+                    "The SHA-256 hash the downloaded luau-lsp-proxy archive must have.",
+                    nullable(Schema::String),
+                ),
+            ]),
+        ),
+        field(
+            "definitions",
+            "Additional definition file paths to pass to the language server.",
+            array(Schema::String),
+        ),
+        field(
+            "documentation",
+            "Additional documentation file paths to pass to the language server.",
+            array(Schema::String),
+        ),
+        field(
+            "offline",
+            "Whether or not the extension should avoid the network entirely, using only previously downloaded files.",
+            Schema::Bool,
+        ),
+        field(
+            "cache",
+            "Settings for downloaded fflags, definitions and documentation.",
+            Schema::Object(vec![field(
+                "max_age",
+                "How long, in seconds, downloaded files are used before the extension tries to refresh them.",
+                Schema::Integer {
+                    min: 0,
+                    max: i64::MAX,
+                },
+            )]),
+        ),
+        field(
+            "mirrors",
+            "Base URLs and GitHub repositories to download from.",
+            Schema::Object(vec![
+                field(
+                    "fflags",
+                    "The base URL to download fflags from.",
+                    Schema::String,
+                ),
+                field(
+                    "api_docs",
+                    "The base URL to download Roblox API documentation from.",
+                    Schema::String,
+                ),
+                field(
+                    "definitions",
+                    "The base URL to download Roblox definitions from.",
+                    Schema::String,
+                ),
+                field(
+                    "github_api",
+                    "The base URL of the GitHub REST API.",
+                    Schema::String,
+                ),
+                field(
+                    "luau_lsp_repo",
+                    "The GitHub repository to download luau-lsp from.",
+                    Schema::String,
+                ),
+                field(
+                    "proxy_repo",
+                    "The GitHub repository to download luau-lsp-proxy from.",
+                    Schema::String,
+                ),
+            ]),
+        ),
+        field(
+            "runtime",
+            "The non-Roblox runtime the project targets.",
+            nullable(Schema::Enum(&["lune", "zune", "lute"])),
+        ),
+        field(
+            "runtime_version",
+            "The release tag of the runtime to download type definitions for. If null, the latest release is used.",
+            nullable(Schema::String),
+        ),
+        field(
+            "runtime_typedefs",
+            "A directory containing the runtime's type definitions to use instead of downloading them.",
+            nullable(Schema::String),
+        ),
+    ])
+}
+
+/// Returns JSON schemas for the settings, in the format of the `json.schemas` setting of the JSON
+/// language server.
+pub fn json_schemas() -> Value {
+    let defaults = serde_json::to_value(Settings::default()).unwrap_or_default();
+    let mut settings = schema::to_json_schema(&settings_schema(), &defaults);
+    if let Some(properties) = settings["properties"].as_object_mut() {
+        properties.insert(
+            "ext".into(),
+            serde_json::json!({
+                "$ref": "#/definitions/settings",
+                "description": "Extension settings. If present, other extension settings outside of it are ignored.",
+            }),
+        );
+    }
+    serde_json::json!([
+        {
+            "fileMatch": ["zed/settings.json", ".zed/settings.json"],
+            "schema": {
+                "type": "object",
+                "properties": {
+                    "lsp": {
+                        "type": "object",
+                        "properties": {
+                            "luau-lsp": {
+                                "type": "object",
+                                "properties": {
+                                    "settings": { "$ref": "#/definitions/settings" },
+                                },
+                            },
+                        },
+                    },
+                },
+                "definitions": { "settings": settings },
+            },
+        },
+        {
+            "fileMatch": [PROJECT_SETTINGS_FILE_NAME],
+            "schema": schema::to_json_schema(&settings_schema(), &defaults),
+        },
+    ])
+}
+
+/// Reads the project settings file in the root of `worktree`, if there is one.
+fn get_project_settings(worktree: &zed::Worktree) -> Result<Option<Value>> {
+    let Ok(as_str) = worktree.read_text_file(PROJECT_SETTINGS_FILE_NAME) else {
+        return Ok(None);
+    };
+    parse_project_settings(&as_str).map(Some)
+}
+
+/// Parses the contents of a project settings file. Like Zed settings, they may contain comments
+/// and trailing commas.
+fn parse_project_settings(as_str: &str) -> Result<Value> {
+    let value: Value = serde_json_lenient::from_str(as_str)
+        .map_err(|e| format!("failed to parse {PROJECT_SETTINGS_FILE_NAME}: {e}"))?;
+    if !value.is_object() {
+        return Err(format!(
+            "invalid {PROJECT_SETTINGS_FILE_NAME}: settings must be an object, but aren't."
+        ));
+    }
+    for path in PROJECT_SETTINGS_DISALLOWED {
+        if json::get_path(&value, path).is_some() {
+            return Err(format!(
+                "invalid {PROJECT_SETTINGS_FILE_NAME}: `{}` can't be set in project settings files. Set it in your Zed settings instead.",
+                path.join(".")
+            ));
+        }
+    }
+    Ok(value)
+}
+
+/// Reads the extension settings from `settings_val`, which is `lsp.luau-lsp.settings` from the Zed
+/// settings, with the project settings file of `worktree` merged over it. Also returns warnings
+/// about likely mistakes in the settings.
+pub fn get_extension_settings(
+    settings_val: Option<serde_json::Value>,
+    worktree: &zed::Worktree,
+) -> Result<(Settings, Vec<String>)> {
+    let project_settings = get_project_settings(worktree)?;
+
+    let mut value = match settings_val {
+        Some(mut settings_val) => {
+            let Some(settings) = settings_val.as_object_mut() else {
+                return Err(
+                    "invalid luau-lsp settings: `settings` must be an object, but isn't.".into(),
+                );
+            };
+            let luau_lsp_settings = settings.remove("luau-lsp");
+            let mut value = settings.remove("ext").unwrap_or(settings_val);
+            if let Value::Object(o) = &mut value {
+                o.insert(
+                    "luau-lsp".to_string(),
+                    luau_lsp_settings.unwrap_or(Value::Object(Map::new())),
+                );
+            }
+            value
+        }
+        None if project_settings.is_none() => return Ok((Settings::default(), Vec::new())),
+        None => Value::Object(Map::new()),
+    };
+
+    if let Some(project_settings) = project_settings {
+        json::merge(&mut value, project_settings);
+    }
+
+    let mut problems = schema::Problems::default();
+    schema::validate(&value, &settings_schema(), "", &mut problems);
+    if !problems.errors.is_empty() {
+        let mut message = "invalid luau-lsp settings:".to_string();
+        for problem in problems.errors.iter().chain(&problems.warnings) {
+            message.push_str(&format!("\n- {problem}"));
+        }
+        return Err(message);
+    }
+
+    let mut result: Result<Settings> =
+        serde_path_to_error::deserialize(value).map_err(|e| e.to_string());
+    if let Ok(ref mut settings) = result {
+        let types = json::get_or_insert_object(&mut settings.luau_lsp, "types");
+        // Merge luau-lsp.types.definitions and definitions. The former is read by the language
+        // server to e.g. not treat definition files as regular luau files. The latter cannot be
+        // removed for backwards compatibility reasons.
+        let definition_files = json::get_or_insert_array(types, "definitionFiles");
+        let old_definition_count = settings.definitions.len();
+        for def in &mut *definition_files {
+            if let Value::String(s) = def {
+                settings.definitions.push(s.clone())
+            }
+        }
+        for i in 0..old_definition_count {
+            definition_files.push(Value::String(settings.definitions[i].clone()));
+        }
+    }
+    result.map(|settings| (settings, problems.warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fails unless the fields of every object in `schema` are exactly the keys of the matching
+    /// object in `value`.
+    fn assert_fields_match(schema: &Schema, value: &Value, path: &str) {
+        let Schema::Object(fields) = schema else {
+            return;
+        };
+        let Value::Object(object) = value else {
+            panic!("`{path}` is an object in the schema, but not in `Settings`");
+        };
+        let mut schema_keys: Vec<&str> = fields.iter().map(|field| field.name).collect();
+        let mut settings_keys: Vec<&str> = object.keys().map(String::as_str).collect();
+        schema_keys.sort();
+        settings_keys.sort();
+        assert_eq!(
+            schema_keys, settings_keys,
+            "the fields of `{path}` differ between the schema and `Settings`"
+        );
+        for field in fields {
+            let path = match path {
+                "" => field.name.to_string(),
+                _ => format!("{path}.{}", field.name),
+            };
+            assert_fields_match(&field.schema, &object[field.name], &path);
+        }
+    }
+
+    #[test]
+    fn project_settings_accept_comments_and_trailing_commas() {
+        let value = parse_project_settings(
+            "// Settings for this project.\n{\n  /* Studio plugin */\n  \"plugin\": { \"enabled\": true, },\n  \"definitions\": [\"a.d.luau\",],\n}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({ "plugin": { "enabled": true }, "definitions": ["a.d.luau"] })
+        );
+    }
+
+    #[test]
+    fn project_settings_reject_disallowed_settings() {
+        let error = parse_project_settings(r#"{ "binary": { "path": "evil" } }"#).unwrap_err();
+        assert!(error.contains("`binary.path`"), "{error}");
+    }
+
+    #[test]
+    fn schema_matches_settings() {
+        let defaults = serde_json::to_value(Settings::default()).unwrap();
+        assert_fields_match(&settings_schema(), &defaults, "");
+    }
+}
@@ -0,0 +1,182 @@
+use crate::github::VersionSpec;
+use zed_extension_api::{self as zed, Result};
+
+/// The toolchain manager manifests the luau-lsp version can be pinned in, in the order they're
+/// checked.
+const MANIFESTS: &[Manifest] = &[
+    Manifest {
+        file_name: "rokit.toml",
+        format: Format::Spec,
+    },
+    Manifest {
+        file_name: "aftman.toml",
+        format: Format::Spec,
+    },
+    Manifest {
+        file_name: "foreman.toml",
+        format: Format::Foreman,
+    },
+];
+
+struct Manifest {
+    file_name: &'static str,
+    format: Format,
+}
+
+enum Format {
+    /// Tools are written as `name = "owner/repo@version"`, like in Rokit and Aftman.
+    Spec,
+    /// Tools are written as `name = { github = "owner/repo", version = "requirement" }`.
+    Foreman,
+}
+
+/// A luau-lsp version pinned in a toolchain manager manifest.
+pub struct PinnedVersion {
+    pub file_name: &'static str,
+    pub version: VersionSpec,
+}
+
+/// Returns the version of one of `repos` pinned in the first toolchain manager manifest in the
+/// worktree root that pins one. Manifests that can't be parsed are skipped with a warning.
+pub fn find_pinned_version(
+    worktree: &zed::Worktree,
+    repos: &[&str],
+    warnings: &mut Vec<String>,
+) -> Option<PinnedVersion> {
+    MANIFESTS.iter().find_map(|manifest| {
+        let text = worktree.read_text_file(manifest.file_name).ok()?;
+        parse_pinned_version(manifest, &text, repos).unwrap_or_else(|e| {
+            warnings.push(e);
+            None
+        })
+    })
+}
+
+/// Returns the version of one of `repos` pinned in `text`, the contents of `manifest`.
+fn parse_pinned_version(
+    manifest: &Manifest,
+    text: &str,
+    repos: &[&str],
+) -> Result<Option<PinnedVersion>> {
+    let table: toml::Table = text
+        .parse()
+        .map_err(|e| format!("failed to parse {}: {e}", manifest.file_name))?;
+    let Some(tools) = table.get("tools").and_then(|tools| tools.as_table()) else {
+        return Ok(None);
+    };
+    Ok(tools.values().find_map(|tool| {
+        let (repo, version) = match manifest.format {
+            Format::Spec => {
+                let (repo, version) = tool.as_str()?.split_once('@')?;
+                (repo, VersionSpec::parse(version))
+            }
+            Format::Foreman => {
+                let repo = tool
+                    .get("github")
+                    .or_else(|| tool.get("source"))?
+                    .as_str()?;
+                let version = tool.get("version")?.as_str()?;
+                let version = match semver::VersionReq::parse(version) {
+                    Ok(req) => VersionSpec::Requirement(req),
+                    Err(_) => VersionSpec::parse(version),
+                };
+                (repo, version)
+            }
+        };
+        repos
+            .iter()
+            .any(|r| r.eq_ignore_ascii_case(repo.trim()))
+            .then_some(PinnedVersion {
+                file_name: manifest.file_name,
+                version,
+            })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPOS: &[&str] = &["JohnnyMorganz/luau-lsp"];
+
+    /// Returns the version pinned in `text` as the manifest `file_name`, as written in settings.
+    fn pinned(file_name: &str, text: &str) -> Option<String> {
+        let manifest = MANIFESTS
+            .iter()
+            .find(|manifest| manifest.file_name == file_name)
+            .unwrap();
+        parse_pinned_version(manifest, text, REPOS)
+            .unwrap()
+            .map(|pinned| {
+                assert_eq!(pinned.file_name, file_name);
+                pinned.version.to_string()
+            })
+    }
+
+    #[test]
+    fn reads_rokit_manifests() {
+        let text = r#"
+[tools]
+rojo = "rojo-rbx/rojo@7.4.4"
+luau-lsp = "JohnnyMorganz/luau-lsp@1.40.0"
+"#;
+        assert_eq!(pinned("rokit.toml", text).as_deref(), Some("1.40.0"));
+    }
+
+    #[test]
+    fn reads_aftman_manifests_under_any_tool_name() {
+        let text = r#"
+[tools]
+lsp = "johnnymorganz/luau-lsp@1.39.2"
+"#;
+        assert_eq!(pinned("aftman.toml", text).as_deref(), Some("1.39.2"));
+    }
+
+    #[test]
+    fn reads_foreman_versions_as_requirements() {
+        let text = r#"
+[tools]
+rojo = { source = "rojo-rbx/rojo", version = "7.4.4" }
+luau-lsp = { github = "JohnnyMorganz/luau-lsp", version = "=1.40.0" }
+"#;
+        assert_eq!(pinned("foreman.toml", text).as_deref(), Some("=1.40.0"));
+
+        let text = r#"
+[tools]
+luau-lsp = { source = "JohnnyMorganz/luau-lsp", version = "1.40.0" }
+"#;
+        assert_eq!(pinned("foreman.toml", text).as_deref(), Some("^1.40.0"));
+    }
+
+    #[test]
+    fn ignores_other_repos() {
+        let text = r#"
+[tools]
+rojo = "rojo-rbx/rojo@7.4.4"
+"#;
+        assert_eq!(pinned("rokit.toml", text), None);
+        assert_eq!(pinned("rokit.toml", "name = \"my-game\""), None);
+    }
+
+    #[test]
+    fn ignores_tools_in_the_other_format() {
+        let text = r#"
+[tools]
+luau-lsp = { github = "JohnnyMorganz/luau-lsp", version = "1.40.0" }
+"#;
+        assert_eq!(pinned("rokit.toml", text), None);
+        let text = r#"
+[tools]
+luau-lsp = "JohnnyMorganz/luau-lsp@1.40.0"
+"#;
+        assert_eq!(pinned("foreman.toml", text), None);
+    }
+
+    #[test]
+    fn reports_invalid_manifests() {
+        let error = parse_pinned_version(&MANIFESTS[0], "[tools", REPOS)
+            .err()
+            .unwrap();
+        assert!(error.starts_with("failed to parse rokit.toml: "), "{error}");
+    }
+}
@@ -0,0 +1,64 @@
+use crate::installer::Tool;
+use crate::platform::Target;
+use zed::{Architecture, Os};
+use zed_extension_api as zed;
+
+pub const LUAU_LSP: Tool = Tool {
+    name: "luau-lsp",
+    install_dir: "luau-lsp-binaries",
+    asset_names: luau_lsp_asset_names,
+};
+
+// Pre-releases are installed separately so that switching channels doesn't remove the other
+// channel's installs.
+pub const LUAU_LSP_PRERELEASE: Tool = Tool {
+    install_dir: "luau-lsp-prerelease-binaries",
+    ..LUAU_LSP
+};
+
+pub const LUAU_LSP_PROXY: Tool = Tool {
+    name: "luau-lsp-proxy",
+    install_dir: "proxy-binaries",
+    asset_names: proxy_asset_names,
+};
+
+fn luau_lsp_asset_names(target: Target, _tag: &str) -> Vec<String> {
+    let names: &[&str] = match (target.os, target.arch, target.musl) {
+        // The macOS build is a universal binary.
+        (Os::Mac, _, _) => &["luau-lsp-macos.zip"],
+        (Os::Windows, Architecture::X8664, _) => &["luau-lsp-win64.zip"],
+        // Windows on ARM can run x86-64 binaries through emulation.
+        (Os::Windows, Architecture::Aarch64, _) => {
+            &["luau-lsp-win-arm64.zip", "luau-lsp-win64.zip"]
+        }
+        (Os::Linux, Architecture::X8664, false) => {
+            &["luau-lsp-linux-x86_64.zip", "luau-lsp-linux.zip"]
+        }
+        (Os::Linux, Architecture::Aarch64, false) => &["luau-lsp-linux-arm64.zip"],
+        (Os::Linux, Architecture::X8664, true) => &["luau-lsp-linux-x86_64-musl.zip"],
+        (Os::Linux, Architecture::Aarch64, true) => &["luau-lsp-linux-arm64-musl.zip"],
+        (_, Architecture::X86, _) => &[],
+    };
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn proxy_asset_names(target: Target, tag: &str) -> Vec<String> {
+    let version = tag.strip_prefix('v').unwrap_or(tag);
+    let os = match (target.os, target.musl) {
+        (Os::Mac, _) => "macos",
+        (Os::Windows, _) => "windows",
+        (Os::Linux, false) => "linux",
+        (Os::Linux, true) => "linux-musl",
+    };
+    let archs: &[&str] = match (target.os, target.arch) {
+        (_, Architecture::X8664) => &["x86_64"],
+        // Windows on ARM can run x86-64 binaries through emulation.
+        (Os::Windows, Architecture::Aarch64) => &["aarch64", "x86_64"],
+        (_, Architecture::Aarch64) => &["aarch64"],
+        (_, Architecture::X86) => &[],
+    };
+    archs
+        .iter()
+        .map(|arch| format!("luau-lsp-proxy-{version}-{os}-{arch}.zip"))
+        .collect()
+}